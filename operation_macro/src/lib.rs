@@ -1,44 +1,153 @@
 extern crate proc_macro;
 
 use proc_macro::TokenStream;
-use quote::quote;
-use syn::{parse_macro_input, ItemMod};
-
-#[proc_macro]
-pub fn generate_insert_gates(input: TokenStream) -> TokenStream {
-    let input = parse_macro_input!(input as ItemMod);
-    let mod_name = &input.ident;
-
-    // Extract function names from the module
-    let mut gate_names = Vec::new();
-
-    if let Some((_, items)) = input.content {
-        for item in items {
-            if let syn::Item::Fn(func) = item {
-                let func_name = func.sig.ident.to_string();
-                gate_names.push(func_name);
-            }
+use proc_macro2::Span;
+use quote::{format_ident, quote};
+use syn::{parse_macro_input, spanned::Spanned, FnArg, Ident, ItemFn, LitInt, LitStr, Pat, Type};
+
+/// Turn a function computing a gate's matrix into a gate of the library.
+///
+/// ```ignore
+/// #[gate(name = "rzz", qubits = 2, params = 1)]
+/// pub fn rzz(theta: f64) -> DMatrix<c64> { ... }
+/// ```
+///
+/// The function takes one `f64` per parameter; `params` defaults to 0 and
+/// must match the number of arguments. In its place the attribute generates
+///
+/// - `rzz(theta: f64) -> Gate`, building the gate,
+/// - `rzz_matrix(params: &[f64]) -> DMatrix<c64>`, the gate's `MatrixFn`,
+/// - `register_rzz(registry: &mut GateRegistry)`, adding the gate to a
+///   registry under `name`.
+#[proc_macro_attribute]
+pub fn gate(attr: TokenStream, item: TokenStream) -> TokenStream {
+    let mut name: Option<LitStr> = None;
+    let mut qubits: Option<LitInt> = None;
+    let mut params: Option<LitInt> = None;
+    let properties = syn::meta::parser(|meta| {
+        if meta.path.is_ident("name") {
+            name = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("qubits") {
+            qubits = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("params") {
+            params = Some(meta.value()?.parse()?);
+        } else {
+            return Err(meta.error("expected `name`, `qubits` or `params`"));
         }
+        Ok(())
+    });
+    parse_macro_input!(attr with properties);
+    let function = parse_macro_input!(item as ItemFn);
+
+    expand_gate(name, qubits, params, function)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+fn expand_gate(
+    name: Option<LitStr>,
+    qubits: Option<LitInt>,
+    params: Option<LitInt>,
+    function: ItemFn,
+) -> syn::Result<proc_macro2::TokenStream> {
+    let name = name.ok_or_else(|| syn::Error::new(Span::call_site(), "missing gate `name`"))?;
+    let qubits = qubits.ok_or_else(|| syn::Error::new(Span::call_site(), "missing gate `qubits`"))?;
+    let num_qubits: usize = qubits.base10_parse()?;
+    if num_qubits == 0 {
+        return Err(syn::Error::new(qubits.span(), "a gate acts on at least one qubit"));
+    }
+    let num_params: usize = match &params {
+        Some(params) => params.base10_parse()?,
+        None => 0,
+    };
+
+    let args = parameter_names(&function)?;
+    if args.len() != num_params {
+        return Err(syn::Error::new(
+            function.sig.inputs.span(),
+            format!(
+                "gate `{}` declares {} parameter(s) but the function takes {}",
+                name.value(),
+                num_params,
+                args.len()
+            ),
+        ));
     }
 
-    // Generate the map insertion code
-    let insertions = gate_names.iter().map(|name| {
-        let ident = syn::Ident::new(name, proc_macro2::Span::call_site());
-        quote! {
-            mtx_map.insert(
-                singleton::#ident().name().to_string(),
-                singleton::#ident().to_matrix(),
+    let ItemFn {
+        attrs,
+        vis,
+        sig,
+        block,
+    } = function;
+    let ident = &sig.ident;
+    let output = &sig.output;
+    let matrix_ident = format_ident!("{}_matrix", ident);
+    let register_ident = format_ident!("register_{}", ident);
+    let dim = 1usize << num_qubits;
+    let matrix_doc = format!("Matrix of the `{}` gate from its parameters.", name.value());
+    let register_doc = format!("Register the `{}` gate.", name.value());
+    let arity_message = format!("gate `{}` takes {} parameter(s)", name.value(), num_params);
+    // only parameterized gates need their matrix recomputed on binding
+    let matrix_fn = (num_params > 0).then(|| quote!(.matrix_fn(#matrix_ident)));
+
+    Ok(quote! {
+        #(#attrs)*
+        #vis fn #ident(#(#args: f64),*) -> ::qiskit_parser::operations::Gate {
+            let matrix = #matrix_ident(&[#(#args),*]);
+            debug_assert_eq!(
+                matrix.shape(),
+                (#dim, #dim),
+                "gate `{}` acts on {} qubit(s)",
+                #name,
+                #num_qubits
             );
+            ::qiskit_parser::operations::GateBuilder::new()
+                .name(#name.to_string())
+                .params(vec![#(::qiskit_parser::parameter::Param::Value(#args)),*])
+                .unit(::qiskit_parser::operations::TimeUnit::DT)
+                .matrix(matrix)
+                #matrix_fn
+                .build()
         }
-    });
 
-    let expanded = quote! {
-        {
-            let mut mtx_map = std::collections::HashMap::new();
-            #(#insertions)*
-            mtx_map
+        #[doc = #matrix_doc]
+        #vis fn #matrix_ident(params: &[f64]) #output {
+            let [#(#args),*]: [f64; #num_params] = params
+                .try_into()
+                .expect(#arity_message);
+            #block
         }
-    };
 
-    TokenStream::from(expanded)
-}
\ No newline at end of file
+        #[doc = #register_doc]
+        #vis fn #register_ident(registry: &mut ::qiskit_parser::gates::GateRegistry) {
+            registry.register(#name, #num_qubits, Some(#num_params), |params: &[f64]| {
+                let [#(#args),*]: [f64; #num_params] = params
+                    .try_into()
+                    .expect(#arity_message);
+                #ident(#(#args),*)
+            });
+        }
+    })
+}
+
+/// Names of the function's arguments, which must all be plain `f64`s.
+fn parameter_names(function: &ItemFn) -> syn::Result<Vec<Ident>> {
+    function
+        .sig
+        .inputs
+        .iter()
+        .map(|input| {
+            let FnArg::Typed(arg) = input else {
+                return Err(syn::Error::new(input.span(), "gates cannot take `self`"));
+            };
+            let Pat::Ident(pat) = arg.pat.as_ref() else {
+                return Err(syn::Error::new(arg.pat.span(), "expected a parameter name"));
+            };
+            match arg.ty.as_ref() {
+                Type::Path(ty) if ty.path.is_ident("f64") => Ok(pat.ident.clone()),
+                ty => Err(syn::Error::new(ty.span(), "gate parameters must be `f64`")),
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,235 @@
+use nalgebra::base::DMatrix;
+
+use crate::c64;
+use crate::circuit_instruction::CircuitInstruction;
+use crate::operations::{Gate, Operation};
+use crate::quantum_circuit::QuantumCircuit;
+
+/// Tolerances for approximate comparisons. Two numbers `a` and `b` are close
+/// when `|a - b| <= atol + rtol * |b|`, as in numpy's `allclose`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Tolerance {
+    pub atol: f64,
+    pub rtol: f64,
+    /// Compare matrices up to a global phase
+    pub ignore_global_phase: bool,
+}
+
+impl Default for Tolerance {
+    fn default() -> Self {
+        Tolerance {
+            atol: 1e-8,
+            rtol: 1e-5,
+            ignore_global_phase: false,
+        }
+    }
+}
+
+impl Tolerance {
+    pub fn new(atol: f64, rtol: f64) -> Self {
+        Tolerance {
+            atol,
+            rtol,
+            ignore_global_phase: false,
+        }
+    }
+
+    /// The same tolerances, comparing matrices up to a global phase.
+    pub fn ignoring_global_phase(self) -> Self {
+        Tolerance {
+            ignore_global_phase: true,
+            ..self
+        }
+    }
+
+    pub fn is_close(&self, a: f64, b: f64) -> bool {
+        (a - b).abs() <= self.atol + self.rtol * b.abs()
+    }
+
+    pub fn is_close_complex(&self, a: c64, b: c64) -> bool {
+        (a - b).norm() <= self.atol + self.rtol * b.norm()
+    }
+}
+
+/// Equality up to floating-point error.
+///
+/// Gates built in different ways, e.g. `h` and `u(π/2, 0, π)`, have matrices
+/// that differ in their last bits, so derived `PartialEq` tells them apart.
+pub trait ApproxEq {
+    fn approx_eq_with(&self, other: &Self, tolerance: &Tolerance) -> bool;
+
+    /// Compare with the default [`Tolerance`].
+    fn approx_eq(&self, other: &Self) -> bool {
+        self.approx_eq_with(other, &Tolerance::default())
+    }
+
+    /// Compare with the default [`Tolerance`], up to a global phase.
+    fn approx_eq_up_to_phase(&self, other: &Self) -> bool {
+        self.approx_eq_with(other, &Tolerance::default().ignoring_global_phase())
+    }
+}
+
+impl ApproxEq for DMatrix<c64> {
+    fn approx_eq_with(&self, other: &Self, tolerance: &Tolerance) -> bool {
+        if self.shape() != other.shape() {
+            return false;
+        }
+        let phase = if tolerance.ignore_global_phase {
+            // the phase aligning `other` with `self` best
+            let overlap = other.dotc(self);
+            if overlap.norm() == 0.0 {
+                c64::new(1.0, 0.0)
+            } else {
+                overlap / overlap.norm()
+            }
+        } else {
+            c64::new(1.0, 0.0)
+        };
+        self.iter()
+            .zip(other.iter())
+            .all(|(a, b)| tolerance.is_close_complex(*a, b * phase))
+    }
+}
+
+/// Bound gates are equal when they have the same matrix and duration, whatever
+/// their name, parameters or Hamiltonian. Gates with symbolic parameters only
+/// have placeholder matrices, so they must also have the same name and
+/// parameters.
+impl ApproxEq for Gate {
+    fn approx_eq_with(&self, other: &Self, tolerance: &Tolerance) -> bool {
+        if !(self.is_bound() && other.is_bound())
+            && (self.name() != other.name() || self.params() != other.params())
+        {
+            return false;
+        }
+        let durations = match (self.duration(), other.duration()) {
+            (None, None) => true,
+            (Some(a), Some(b)) if self.unit() == other.unit() => tolerance.is_close(a, b),
+            (Some(a), Some(b)) => match (
+                self.unit().to_seconds(a, None),
+                other.unit().to_seconds(b, None),
+            ) {
+                (Some(a), Some(b)) => tolerance.is_close(a, b),
+                // `dt` is unknown, so samples and seconds cannot be compared
+                _ => false,
+            },
+            _ => false,
+        };
+        durations && self.matrix().approx_eq_with(other.matrix(), tolerance)
+    }
+}
+
+impl ApproxEq for Operation {
+    fn approx_eq_with(&self, other: &Self, tolerance: &Tolerance) -> bool {
+        match (self, other) {
+            (Operation::Gate(a), Operation::Gate(b)) => a.approx_eq_with(b, tolerance),
+            (Operation::Delay(a), Operation::Delay(b)) => {
+                a.unit() == b.unit() && tolerance.is_close(a.duration(), b.duration())
+            }
+            _ => self == other,
+        }
+    }
+}
+
+impl ApproxEq for CircuitInstruction {
+    fn approx_eq_with(&self, other: &Self, tolerance: &Tolerance) -> bool {
+        self.qubits() == other.qubits()
+            && self.clbits() == other.clbits()
+            && self.operation().approx_eq_with(other.operation(), tolerance)
+    }
+}
+
+/// Circuits are equal when they have the same bits and registers and
+/// approximately equal instructions. Parse warnings are not compared. With
+/// `ignore_global_phase` every gate may differ by its own phase, so the
+/// circuits are equal up to a global phase.
+impl ApproxEq for QuantumCircuit {
+    fn approx_eq_with(&self, other: &Self, tolerance: &Tolerance) -> bool {
+        self.qubits() == other.qubits()
+            && self.clbits() == other.clbits()
+            && self.ancillas() == other.ancillas()
+            && self.qregs() == other.qregs()
+            && self.cregs() == other.cregs()
+            && self.instructions().len() == other.instructions().len()
+            && self
+                .instructions()
+                .iter()
+                .zip(other.instructions())
+                .all(|(a, b)| a.approx_eq_with(b, tolerance))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+    use crate::gates::{parameterized, singleton};
+    use crate::operations::TimeUnit;
+    use crate::parameter::{Param, Parameter, ParameterExpression};
+
+    /// Gates are compared by their matrix, not how they were built
+    #[test]
+    fn test_gates() {
+        let h = singleton::hadamard();
+        let u = parameterized::u(PI / 2.0, 0.0, PI);
+        assert_ne!(h, u);
+        assert!(h.approx_eq(&u));
+        assert!(!h.approx_eq(&singleton::x()));
+        assert!(!h.approx_eq(&singleton::cx()));
+
+        // RZ(θ) is P(θ) up to a phase of e^{-iθ/2}
+        let rz = parameterized::rz(0.3);
+        let p = parameterized::p(0.3);
+        assert!(!rz.approx_eq(&p));
+        assert!(rz.approx_eq_up_to_phase(&p));
+        assert!(!rz.approx_eq_up_to_phase(&parameterized::p(0.4)));
+
+        let loose = Tolerance::new(1e-3, 0.0);
+        assert!(rz.approx_eq_with(&parameterized::rz(0.3001), &loose));
+        assert!(!rz.approx_eq(&parameterized::rz(0.3001)));
+    }
+
+    /// Durations are compared in seconds when the units differ
+    #[test]
+    fn test_durations() {
+        let gate = |duration, unit| {
+            singleton::x().builder().duration(duration).unit(unit).build()
+        };
+        assert!(gate(1.0, TimeUnit::US).approx_eq(&gate(1000.0, TimeUnit::NS)));
+        assert!(!gate(1.0, TimeUnit::US).approx_eq(&gate(1.0, TimeUnit::NS)));
+        assert!(!gate(1.0, TimeUnit::US).approx_eq(&gate(1.0, TimeUnit::DT)));
+        assert!(!gate(1.0, TimeUnit::US).approx_eq(&singleton::x()));
+    }
+
+    /// Symbolic gates need the same name and parameters
+    #[test]
+    fn test_symbolic() {
+        let theta = Param::Expression(ParameterExpression::Symbol(Parameter::new("θ".to_string())));
+        let rz = parameterized::rz(0.0).with_params(vec![theta.clone()]);
+        let p = parameterized::p(0.0).with_params(vec![theta]);
+        assert!(rz.approx_eq(&rz.clone()));
+        assert!(!rz.approx_eq(&p));
+        assert!(!rz.approx_eq(&parameterized::rz(0.0)));
+    }
+
+    /// Circuits are compared instruction by instruction
+    #[test]
+    fn test_circuits() {
+        let circuit = |gate: &str, params: &str| {
+            let input = format!("[CircuitInstruction(operation=Instruction(name='{}', num_qubits=1, num_clbits=0, params=[{}]), qubits=(Qubit(QuantumRegister(2, 'q'), 0),), clbits=()), CircuitInstruction(operation=Instruction(name='cx', num_qubits=2, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(2, 'q'), 0), Qubit(QuantumRegister(2, 'q'), 1)), clbits=())]", gate, params);
+            QuantumCircuit::new(input, None)
+        };
+        let bell = circuit("h", "");
+        let bell_u = circuit("u", "1.5707963267948966, 0.0, 3.141592653589793");
+        assert_ne!(bell, bell_u);
+        assert!(bell.approx_eq(&bell_u));
+        assert!(bell.instructions()[0].approx_eq(&bell_u.instructions()[0]));
+        assert!(!bell.approx_eq(&circuit("x", "")));
+
+        let rz = circuit("rz", "0.5");
+        let p = circuit("p", "0.5");
+        assert!(!rz.approx_eq(&p));
+        assert!(rz.approx_eq_up_to_phase(&p));
+    }
+}
@@ -7,8 +7,10 @@ pub enum Bit {
 }
 
 pub trait BitOps : From<Bit> {
-    fn new(name: String, index: usize) -> Self;
+    fn new(name: String, register_size: usize, index: usize) -> Self;
     fn name(&self) -> String;
+    /// Size of the register the bit belongs to
+    fn register_size(&self) -> usize;
     fn index(&self) -> usize;
 }
 
@@ -16,6 +18,7 @@ pub trait BitOps : From<Bit> {
 /// A classical bit
 pub struct Clbit {
     name: String,
+    register_size: usize,
     index: usize,
 }
 
@@ -23,6 +26,7 @@ pub struct Clbit {
 /// A quantum bit
 pub struct Qubit {
     name: String,
+    register_size: usize,
     index: usize,
 }
 
@@ -31,6 +35,7 @@ pub struct Qubit {
 /// register)
 pub struct AncillaQubit {
     name: String,
+    register_size: usize,
     index: usize,
 }
 
@@ -43,6 +48,18 @@ impl From<Bit> for Qubit {
     }
 }
 
+/// Ancilla qubits are qubits of the circuit like any other, matching Qiskit
+/// where `AncillaQubit` subclasses `Qubit`.
+impl From<AncillaQubit> for Qubit {
+    fn from(ancilla: AncillaQubit) -> Self {
+        Qubit {
+            name: ancilla.name,
+            register_size: ancilla.register_size,
+            index: ancilla.index,
+        }
+    }
+}
+
 impl From<Bit> for Clbit {
     fn from(bit: Bit) -> Self {
         match bit {
@@ -62,42 +79,66 @@ impl From<Bit> for AncillaQubit {
 }
 
 impl BitOps for Qubit {
-    fn new(name: String, index: usize) -> Self {
-        Qubit { name, index }
+    fn new(name: String, register_size: usize, index: usize) -> Self {
+        Qubit {
+            name,
+            register_size,
+            index,
+        }
     }
 
     fn name(&self) -> String {
         self.name.clone()
     }
 
+    fn register_size(&self) -> usize {
+        self.register_size
+    }
+
     fn index(&self) -> usize {
         self.index
     }
 }
 
 impl BitOps for Clbit {
-    fn new(name: String, index: usize) -> Self {
-        Clbit { name, index }
+    fn new(name: String, register_size: usize, index: usize) -> Self {
+        Clbit {
+            name,
+            register_size,
+            index,
+        }
     }
 
     fn name(&self) -> String {
         self.name.clone()
     }
 
+    fn register_size(&self) -> usize {
+        self.register_size
+    }
+
     fn index(&self) -> usize {
         self.index
     }
 }
 
 impl BitOps for AncillaQubit {
-    fn new(name: String, index: usize) -> Self {
-        AncillaQubit { name, index }
+    fn new(name: String, register_size: usize, index: usize) -> Self {
+        AncillaQubit {
+            name,
+            register_size,
+            index,
+        }
     }
 
     fn name(&self) -> String {
         self.name.clone()
     }
 
+    fn register_size(&self) -> usize {
+        self.register_size
+    }
+
     fn index(&self) -> usize {
         self.index
     }
@@ -23,12 +23,14 @@ impl CircuitInstruction {
         &self.operation
     }
 
-    /// Get the qubit indices the CircuitInstruction acts on.
+    /// Get the qubit indices the CircuitInstruction acts on. These are positions
+    /// in `QuantumCircuit::qubits()`, in operand order.
     pub fn qubits(&self) -> &Vec<usize> {
         &self.qubits
     }
 
-    /// Get the clbit indices the CircuitInstruction acts on.
+    /// Get the clbit indices the CircuitInstruction acts on. These are positions
+    /// in `QuantumCircuit::clbits()`, in operand order.
     pub fn clbits(&self) -> &Vec<usize> {
         &self.clbits
     }
@@ -0,0 +1,314 @@
+use std::fmt;
+
+use crate::operations::Fidelity;
+use crate::parameter::Parameter;
+use crate::quantum_circuit::{Span, Token};
+
+/// Number of characters shown on either side of an error in a snippet.
+const SNIPPET_RADIUS: usize = 40;
+
+/// The kinds of failure that can occur while reading a Qiskit circuit repr.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParseErrorKind {
+    /// A token was found that does not fit the expected grammar.
+    UnexpectedToken { expected: String, found: Token },
+    /// The input ended while more tokens were still expected.
+    UnexpectedEof { expected: String },
+    /// A bit constructor other than `Qubit`, `Clbit` or `AncillaQubit`.
+    UnknownBitType { found: String },
+    /// A bit in the wrong group, e.g. a `Clbit` among an instruction's qubits.
+    UnexpectedBitType { found: String, group: String },
+    /// An instruction name that is neither a known gate nor a built-in
+    /// operation.
+    UnknownOperation { name: String },
+    /// An operation given the wrong number of parameters.
+    InvalidParams {
+        name: String,
+        expected: usize,
+        found: usize,
+    },
+    /// An instruction whose `num_qubits`/`num_clbits` disagrees with the
+    /// number of operands it was given.
+    OperandCountMismatch {
+        name: String,
+        group: String,
+        declared: usize,
+        found: usize,
+    },
+    /// A `measure` whose qubits and clbits cannot be paired up.
+    MeasureOperandMismatch { qubits: usize, clbits: usize },
+    /// A `delay` whose duration is not a number, e.g. a parameter.
+    NonNumericDuration { found: String },
+    /// A gate whose matrix is not `2^num_qubits` square.
+    MatrixDimensionMismatch {
+        name: String,
+        num_qubits: usize,
+        dimension: (usize, usize),
+    },
+    /// Every instruction that failed validation, each located by its own
+    /// error.
+    Validation { errors: Vec<ParseError> },
+    /// A numeric literal that could not be read as a float.
+    InvalidNumber { literal: String },
+    /// A string literal that is missing its closing quote.
+    UnterminatedString,
+}
+
+/// Error produced while reading a Qiskit circuit repr.
+///
+/// Carries the location of the offending input and, once the parser has
+/// attached the source, a snippet of the input with a caret under the span.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ParseError {
+    kind: ParseErrorKind,
+    span: Span,
+    instruction: Option<usize>,
+    snippet: Option<String>,
+}
+
+impl ParseError {
+    pub fn new(kind: ParseErrorKind, span: Span) -> Self {
+        ParseError {
+            kind,
+            span,
+            instruction: None,
+            snippet: None,
+        }
+    }
+
+    /// Record the index of the `CircuitInstruction` that failed to parse.
+    pub fn with_instruction(mut self, index: usize) -> Self {
+        self.instruction = Some(index);
+        self
+    }
+
+    /// Render a snippet of `source` around the error span.
+    pub fn with_source(mut self, source: &[char]) -> Self {
+        self.snippet = Some(render_snippet(source, self.span));
+        self
+    }
+
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+
+    /// Character range of the input that caused the error.
+    pub fn span(&self) -> Span {
+        self.span
+    }
+
+    /// Index of the failing `CircuitInstruction`, if the error occurred
+    /// inside one.
+    pub fn instruction(&self) -> Option<usize> {
+        self.instruction
+    }
+
+    pub fn snippet(&self) -> Option<&str> {
+        self.snippet.as_deref()
+    }
+}
+
+fn render_snippet(source: &[char], span: Span) -> String {
+    let start = span.start.min(source.len());
+    let end = span.end.clamp(start, source.len());
+    let window_start = start.saturating_sub(SNIPPET_RADIUS);
+    let window_end = (end + SNIPPET_RADIUS).min(source.len());
+
+    let mut line = String::new();
+    let mut offset = 0;
+    if window_start > 0 {
+        line.push_str("...");
+        offset += 3;
+    }
+    line.extend(&source[window_start..window_end]);
+    if window_end < source.len() {
+        line.push_str("...");
+    }
+
+    let caret_len = (end - start).max(1);
+    let caret = format!(
+        "{}{}",
+        " ".repeat(offset + start - window_start),
+        "^".repeat(caret_len)
+    );
+    format!("{}\n{}", line, caret)
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseErrorKind::UnexpectedToken { expected, found } => {
+                write!(f, "expected {} but found {:?}", expected, found)
+            }
+            ParseErrorKind::UnexpectedEof { expected } => {
+                write!(f, "expected {} but reached end of input", expected)
+            }
+            ParseErrorKind::UnknownBitType { found } => {
+                write!(f, "unknown bit type '{}'", found)
+            }
+            ParseErrorKind::UnexpectedBitType { found, group } => {
+                write!(f, "unexpected bit type '{}' in {}", found, group)
+            }
+            ParseErrorKind::UnknownOperation { name } => {
+                write!(f, "unknown operation '{}'", name)
+            }
+            ParseErrorKind::InvalidParams {
+                name,
+                expected,
+                found,
+            } => write!(
+                f,
+                "'{}' expects {} parameter(s) but got {}",
+                name, expected, found
+            ),
+            ParseErrorKind::OperandCountMismatch {
+                name,
+                group,
+                declared,
+                found,
+            } => write!(
+                f,
+                "'{}' declares {} {} but is applied to {}",
+                name, declared, group, found
+            ),
+            ParseErrorKind::MeasureOperandMismatch { qubits, clbits } => write!(
+                f,
+                "'measure' pairs {} qubit(s) with {} clbit(s)",
+                qubits, clbits
+            ),
+            ParseErrorKind::NonNumericDuration { found } => {
+                write!(f, "delay duration must be a number but found '{}'", found)
+            }
+            ParseErrorKind::MatrixDimensionMismatch {
+                name,
+                num_qubits,
+                dimension,
+            } => write!(
+                f,
+                "'{}' acts on {} qubit(s) but its matrix is {}x{}",
+                name, num_qubits, dimension.0, dimension.1
+            ),
+            ParseErrorKind::Validation { errors } => {
+                write!(f, "{} instruction check(s) failed:", errors.len())?;
+                for error in errors {
+                    write!(f, "\n{}", error)?;
+                }
+                Ok(())
+            }
+            ParseErrorKind::InvalidNumber { literal } => {
+                write!(f, "invalid number '{}'", literal)
+            }
+            ParseErrorKind::UnterminatedString => write!(f, "unterminated string literal"),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.kind)?;
+        // each collected error carries its own location
+        if let ParseErrorKind::Validation { .. } = self.kind {
+            return Ok(());
+        }
+        if let Some(index) = self.instruction {
+            write!(f, " in instruction {}", index)?;
+        }
+        write!(f, " at character {}", self.span.start)?;
+        if let Some(snippet) = &self.snippet {
+            write!(f, "\n{}", snippet)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Errors produced while assigning values to circuit parameters.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParameterError {
+    /// A positional binding with a different number of values than the
+    /// circuit has parameters.
+    LengthMismatch { expected: usize, found: usize },
+    /// A value given for a parameter the circuit does not contain.
+    UnknownParameter(Parameter),
+}
+
+impl fmt::Display for ParameterError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParameterError::LengthMismatch { expected, found } => write!(
+                f,
+                "expected {} parameter value(s) but got {}",
+                expected, found
+            ),
+            ParameterError::UnknownParameter(parameter) => {
+                write!(f, "parameter '{}' is not in the circuit", parameter)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ParameterError {}
+
+/// Why a gate's Hamiltonian could not be shown to generate its matrix.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Mismatch {
+    /// The gate has no Hamiltonian.
+    MissingHamiltonian { name: String },
+    /// The gate has no duration, or one in `DT` whose length is unknown.
+    UnknownDuration { name: String },
+    /// The gate's matrix and Hamiltonian are placeholders until its
+    /// parameters are bound.
+    UnboundParameters { name: String },
+    /// A component of the Hamiltonian acts on a different space than the
+    /// matrix.
+    DimensionMismatch {
+        name: String,
+        matrix: usize,
+        hamiltonian: usize,
+    },
+    /// The evolution under the Hamiltonian is too far from the matrix.
+    Fidelity {
+        name: String,
+        fidelity: Fidelity,
+        tolerance: f64,
+    },
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Mismatch::MissingHamiltonian { name } => {
+                write!(f, "gate '{}' has no Hamiltonian", name)
+            }
+            Mismatch::UnknownDuration { name } => {
+                write!(f, "gate '{}' has no duration in physical units", name)
+            }
+            Mismatch::UnboundParameters { name } => {
+                write!(f, "gate '{}' has unbound parameters", name)
+            }
+            Mismatch::DimensionMismatch {
+                name,
+                matrix,
+                hamiltonian,
+            } => write!(
+                f,
+                "gate '{}' has a {}-dimensional matrix but a {}-dimensional Hamiltonian",
+                name, matrix, hamiltonian
+            ),
+            Mismatch::Fidelity {
+                name,
+                fidelity,
+                tolerance,
+            } => write!(
+                f,
+                "Hamiltonian of gate '{}' generates its matrix with process fidelity {}, below 1 - {}",
+                name,
+                fidelity.value(),
+                tolerance
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Mismatch {}
@@ -1 +1,7 @@
 pub mod singleton;
+pub mod parameterized;
+pub mod ion;
+pub mod registry;
+
+pub use operation_macro::gate;
+pub use registry::{GateConstructor, GateDefinition, GateRegistry};
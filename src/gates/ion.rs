@@ -0,0 +1,461 @@
+use std::f64::consts::PI;
+
+use nalgebra::base::DMatrix;
+use crate::c64;
+
+use crate::operations::{
+    Gate, GateBuilder, Hamiltonian, HamiltonianComponent, MatrixFn, TimeDependence, TimeUnit,
+};
+use crate::parameter::Param;
+
+use super::GateRegistry;
+
+/// Largest number of qubits a global Mølmer–Sørensen gate is built for. Its
+/// dense matrix grows as `4^n`, so `ms` instructions with one parameter on
+/// more qubits are not resolved to the global gate: the parser reports them as
+/// a mismatch with the matrix of the closest definition, without building it.
+pub const MAX_GMS_QUBITS: usize = 10;
+
+/// Definition of a trapped-ion native gate.
+#[derive(Debug, Clone, Copy)]
+pub struct Definition {
+    pub num_qubits: usize,
+    pub num_params: usize,
+    pub matrix_fn: MatrixFn,
+}
+
+/// Names emitted by Qiskit ion providers with their number of qubits, number
+/// of parameters and matrix function. Phases are in turns, as in IonQ's
+/// provider. Qiskit's `RGate` is the parameterized `r`.
+const DEFINITIONS: &[(&str, usize, usize, MatrixFn)] = &[
+    ("gpi", 1, 1, gpi_matrix),
+    ("gpi2", 1, 1, gpi2_matrix),
+    ("ms", 2, 3, ms_matrix),
+    ("zz", 2, 1, zz_matrix),
+];
+
+/// Matrix functions of the global MS gate, indexed by its number of qubits.
+const GMS_DEFINITIONS: [MatrixFn; MAX_GMS_QUBITS + 1] = [
+    gms_matrix_n::<0>,
+    gms_matrix_n::<1>,
+    gms_matrix_n::<2>,
+    gms_matrix_n::<3>,
+    gms_matrix_n::<4>,
+    gms_matrix_n::<5>,
+    gms_matrix_n::<6>,
+    gms_matrix_n::<7>,
+    gms_matrix_n::<8>,
+    gms_matrix_n::<9>,
+    gms_matrix_n::<10>,
+];
+
+/// Definition of the ion-native gate `name` acting on `num_qubits` qubits
+/// with `num_params` parameters.
+///
+/// Both IonQ's two-qubit `ms(φ0, φ1, θ)` and Qiskit's global `MSGate(θ)` are
+/// named `ms`; a single parameter selects the global gate.
+pub fn definition(name: &str, num_qubits: usize, num_params: usize) -> Option<Definition> {
+    if name == "ms" && num_params == 1 && (2..=MAX_GMS_QUBITS).contains(&num_qubits) {
+        return Some(Definition {
+            num_qubits,
+            num_params,
+            matrix_fn: GMS_DEFINITIONS[num_qubits],
+        });
+    }
+    DEFINITIONS
+        .iter()
+        .find(|(gate_name, ..)| *gate_name == name)
+        .map(|(_, num_qubits, num_params, matrix_fn)| Definition {
+            num_qubits: *num_qubits,
+            num_params: *num_params,
+            matrix_fn: *matrix_fn,
+        })
+}
+
+/// Build the ion-native gate `name` on `num_qubits` qubits from possibly
+/// symbolic parameters. Like the other library gates it has no duration or
+/// Hamiltonian, see [`with_hamiltonian`]. Returns `None` if the name is
+/// unknown or the number of parameters is wrong. While parameters are
+/// symbolic the matrix is that of all-zero angles.
+pub fn from_params(name: &str, num_qubits: usize, params: Vec<Param>) -> Option<Gate> {
+    let definition = definition(name, num_qubits, params.len())?;
+    if params.len() != definition.num_params {
+        return None;
+    }
+    let values: Vec<f64> = params.iter().map(|p| p.value().unwrap_or(0.0)).collect();
+    Some(
+        GateBuilder::new()
+            .name(name.to_string())
+            .params(params)
+            .unit(TimeUnit::DT)
+            .matrix((definition.matrix_fn)(&values))
+            .matrix_fn(definition.matrix_fn)
+            .build(),
+    )
+}
+
+/// The constant drive Hamiltonian of the bound ion-native gate `name` on
+/// `num_qubits` qubits, generating it over `duration` seconds. Covers `gpi`,
+/// `gpi2`, `ms`, `zz`, the global `ms` and Qiskit's `r`. Returns `None` for
+/// other gates.
+pub fn hamiltonian(name: &str, num_qubits: usize, params: &[f64], duration: f64) -> Option<Hamiltonian> {
+    let hamiltonian = match (name, num_qubits, params.len()) {
+        ("gpi", 1, 1) => gpi_hamiltonian(params, duration),
+        ("gpi2", 1, 1) => gpi2_hamiltonian(params, duration),
+        ("r", 1, 2) => r_hamiltonian(params, duration),
+        ("ms", 2, 3) => ms_hamiltonian(params, duration),
+        ("ms", n, 1) if n >= 2 => gms_hamiltonian(n, params, duration),
+        ("zz", 2, 1) => zz_hamiltonian(params, duration),
+        _ => return None,
+    };
+    Some(hamiltonian)
+}
+
+/// The gate taking `duration` seconds, with its drive Hamiltonian from
+/// [`hamiltonian`]. Returns `None` if the gate is not ion-native or has
+/// unbound parameters. The Hamiltonian is not recomputed if the gate's
+/// parameters are later replaced.
+pub fn with_hamiltonian(gate: &Gate, duration: f64) -> Option<Gate> {
+    let params = gate.param_values()?;
+    let hamiltonian = hamiltonian(gate.name(), gate.num_qubits(), &params, duration)?;
+    Some(
+        gate.builder()
+            .duration(duration)
+            .unit(TimeUnit::S)
+            .hamiltonian(hamiltonian)
+            .build(),
+    )
+}
+
+/// Register every gate of this module, including the global MS gate for each
+/// supported number of qubits.
+pub fn register(registry: &mut GateRegistry) {
+    // IonQ's `ms` is registered last so it is the one reported when an `ms`
+    // instruction has the wrong number of parameters
+    for num_qubits in 2..=MAX_GMS_QUBITS {
+        registry.register("ms", num_qubits, Some(1), move |params: &[f64]| {
+            build("ms", num_qubits, params)
+        });
+    }
+    for (name, num_qubits, num_params, ..) in DEFINITIONS {
+        registry.register(*name, *num_qubits, Some(*num_params), move |params: &[f64]| {
+            build(name, *num_qubits, params)
+        });
+    }
+}
+
+fn build(name: &str, num_qubits: usize, params: &[f64]) -> Gate {
+    from_params(name, num_qubits, params.iter().map(|p| Param::Value(*p)).collect())
+        .expect("ion gate definition")
+}
+
+/// π rotation about the axis at angle 2πφ in the XY plane.
+pub fn gpi(phi: f64) -> Gate {
+    build("gpi", 1, &[phi])
+}
+
+/// π/2 rotation about the axis at angle 2πφ in the XY plane.
+pub fn gpi2(phi: f64) -> Gate {
+    build("gpi2", 1, &[phi])
+}
+
+/// Mølmer–Sørensen gate with phases φ0 and φ1 on the two qubits, fully
+/// entangling at θ = 1/4.
+pub fn ms(phi0: f64, phi1: f64, theta: f64) -> Gate {
+    build("ms", 2, &[phi0, phi1, theta])
+}
+
+/// exp(-iπθ Z⊗Z)
+pub fn zz(theta: f64) -> Gate {
+    build("zz", 2, &[theta])
+}
+
+/// Global Mølmer–Sørensen gate exp(-iθ/2 Σ X_i X_j) over every pair of
+/// `num_qubits` qubits.
+///
+/// Panics unless `num_qubits` is between 2 and [`MAX_GMS_QUBITS`].
+pub fn gms(num_qubits: usize, theta: f64) -> Gate {
+    assert!(
+        (2..=MAX_GMS_QUBITS).contains(&num_qubits),
+        "the global MS gate is built for 2 to {} qubits, not {}",
+        MAX_GMS_QUBITS,
+        num_qubits
+    );
+    build("ms", num_qubits, &[theta])
+}
+
+// Matrices follow Qiskit's little-endian convention: qubit 0 is the least
+// significant bit of the basis index. Hamiltonians are constant over the
+// requested duration, in rad/s, with the gate given by exp(-iHT).
+
+fn real(x: f64) -> c64 {
+    c64::new(x, 0.0)
+}
+
+fn imag(x: f64) -> c64 {
+    c64::new(0.0, x)
+}
+
+fn cis(phi: f64) -> c64 {
+    c64::from_polar(1.0, phi)
+}
+
+fn square(dim: usize, rows: &[c64]) -> DMatrix<c64> {
+    DMatrix::from_row_slice(dim, dim, rows)
+}
+
+fn pauli_x() -> DMatrix<c64> {
+    square(2, &[real(0.0), real(1.0), real(1.0), real(0.0)])
+}
+
+fn pauli_z() -> DMatrix<c64> {
+    square(2, &[real(1.0), real(0.0), real(0.0), real(-1.0)])
+}
+
+/// cos(φ) X + sin(φ) Y
+fn xy_axis(phi: f64) -> DMatrix<c64> {
+    square(2, &[real(0.0), cis(-phi), cis(phi), real(0.0)])
+}
+
+/// `op` on `qubit` of `num_qubits`, identity elsewhere.
+fn on_qubit(num_qubits: usize, qubit: usize, op: &DMatrix<c64>) -> DMatrix<c64> {
+    (0..num_qubits).rev().fold(DMatrix::identity(1, 1), |acc, q| {
+        if q == qubit {
+            acc.kronecker(op)
+        } else {
+            acc.kronecker(&DMatrix::identity(2, 2))
+        }
+    })
+}
+
+/// Constant Hamiltonian generating exp(-i Σ angle·op) over `duration`
+/// seconds, with one component per term.
+fn constant_hamiltonian(duration: f64, terms: Vec<(f64, DMatrix<c64>)>) -> Hamiltonian {
+    Hamiltonian::new(
+        terms
+            .into_iter()
+            .map(|(angle, op)| HamiltonianComponent::new(TimeDependence::unit(), real(angle / duration), op))
+            .collect(),
+    )
+}
+
+pub fn gpi_matrix(params: &[f64]) -> DMatrix<c64> {
+    xy_axis(2.0 * PI * params[0])
+}
+
+/// Generates GPI up to a global phase of i.
+pub fn gpi_hamiltonian(params: &[f64], duration: f64) -> Hamiltonian {
+    constant_hamiltonian(
+        duration,
+        vec![(PI / 2.0, xy_axis(2.0 * PI * params[0]))],
+    )
+}
+
+pub fn gpi2_matrix(params: &[f64]) -> DMatrix<c64> {
+    let phi = 2.0 * PI * params[0];
+    let h = std::f64::consts::FRAC_1_SQRT_2;
+    square(
+        2,
+        &[real(h), imag(-h) * cis(-phi), imag(-h) * cis(phi), real(h)],
+    )
+}
+
+pub fn gpi2_hamiltonian(params: &[f64], duration: f64) -> Hamiltonian {
+    constant_hamiltonian(
+        duration,
+        vec![(PI / 4.0, xy_axis(2.0 * PI * params[0]))],
+    )
+}
+
+pub fn r_hamiltonian(params: &[f64], duration: f64) -> Hamiltonian {
+    constant_hamiltonian(
+        duration,
+        vec![(params[0] / 2.0, xy_axis(params[1]))],
+    )
+}
+
+/// IonQ's MS matrix, cos(πθ) I - i sin(πθ) P(φ0)⊗P(φ1) with P(φ) the XY axis
+/// at angle 2πφ.
+pub fn ms_matrix(params: &[f64]) -> DMatrix<c64> {
+    let (sum, diff) = (
+        2.0 * PI * (params[0] + params[1]),
+        2.0 * PI * (params[0] - params[1]),
+    );
+    let (c, s) = (real((PI * params[2]).cos()), imag(-(PI * params[2]).sin()));
+    let o = real(0.0);
+    square(
+        4,
+        &[
+            c, o, o, s * cis(-sum),
+            o, c, s * cis(-diff), o,
+            o, s * cis(diff), c, o,
+            s * cis(sum), o, o, c,
+        ],
+    )
+}
+
+pub fn ms_hamiltonian(params: &[f64], duration: f64) -> Hamiltonian {
+    let axes = xy_axis(2.0 * PI * params[0]).kronecker(&xy_axis(2.0 * PI * params[1]));
+    constant_hamiltonian(duration, vec![(PI * params[2], axes)])
+}
+
+pub fn zz_matrix(params: &[f64]) -> DMatrix<c64> {
+    let angle = PI * params[0];
+    let diagonal = [cis(-angle), cis(angle), cis(angle), cis(-angle)];
+    DMatrix::from_fn(4, 4, |i, j| if i == j { diagonal[i] } else { real(0.0) })
+}
+
+pub fn zz_hamiltonian(params: &[f64], duration: f64) -> Hamiltonian {
+    constant_hamiltonian(
+        duration,
+        vec![(PI * params[0], pauli_z().kronecker(&pauli_z()))],
+    )
+}
+
+/// exp(-iθ/2 Σ_{i<j} X_i X_j). In the X basis the sum is diagonal, with
+/// value (s² - n)/2 for s the sum of the ±1 eigenvalues.
+pub fn gms_matrix(num_qubits: usize, params: &[f64]) -> DMatrix<c64> {
+    let theta = params[0];
+    let dim = 1 << num_qubits;
+    let factor = 1.0 / (dim as f64).sqrt();
+    let hadamard = DMatrix::from_fn(dim, dim, |i: usize, j: usize| {
+        real(if (i & j).count_ones().is_multiple_of(2) { factor } else { -factor })
+    });
+    let phases = DMatrix::from_fn(dim, dim, |i: usize, j| {
+        if i != j {
+            return real(0.0);
+        }
+        let minus = i.count_ones() as f64;
+        let sum = num_qubits as f64 - 2.0 * minus;
+        cis(-theta / 2.0 * (sum * sum - num_qubits as f64) / 2.0)
+    });
+    &hadamard * phases * &hadamard
+}
+
+pub fn gms_hamiltonian(num_qubits: usize, params: &[f64], duration: f64) -> Hamiltonian {
+    let x = pauli_x();
+    let terms = (0..num_qubits)
+        .flat_map(|i| ((i + 1)..num_qubits).map(move |j| (i, j)))
+        .map(|(i, j)| {
+            let op = on_qubit(num_qubits, i, &x) * on_qubit(num_qubits, j, &x);
+            (params[0] / 2.0, op)
+        })
+        .collect();
+    constant_hamiltonian(duration, terms)
+}
+
+fn gms_matrix_n<const N: usize>(params: &[f64]) -> DMatrix<c64> {
+    gms_matrix(N, params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gates::{parameterized, singleton};
+
+    fn assert_close(actual: &DMatrix<c64>, expected: &DMatrix<c64>) {
+        assert_eq!(actual.shape(), expected.shape());
+        assert!(
+            (actual - expected).norm() < 1e-12,
+            "matrices differ:\n{}\n{}",
+            actual,
+            expected
+        );
+    }
+
+    /// exp(-iHT) for a Hamiltonian whose components commute, via the
+    /// eigendecomposition of the Hermitian sum.
+    fn evolve(gate: &Gate) -> DMatrix<c64> {
+        let seconds = gate.to_seconds(None).unwrap();
+        let hamiltonian = gate.hamiltonian().unwrap();
+        let total = hamiltonian
+            .components()
+            .iter()
+            .map(|component| component.calculate(0.0))
+            .reduce(|a, b| a + b)
+            .unwrap();
+        let eigen = (total * real(seconds)).symmetric_eigen();
+        let phases = eigen.eigenvalues.map(|value| cis(-value));
+        &eigen.eigenvectors * DMatrix::from_diagonal(&phases) * eigen.eigenvectors.adjoint()
+    }
+
+    /// Values from IonQ's provider for the given phases
+    #[test]
+    fn test_matrices() {
+        let (o, i) = (real(0.0), imag(1.0));
+        let h = real(std::f64::consts::FRAC_1_SQRT_2);
+        assert_close(&gpi(0.0).to_matrix(), &singleton::x().to_matrix());
+        assert_close(&gpi(0.25).to_matrix(), &singleton::y().to_matrix());
+        assert_close(&gpi2(0.0).to_matrix(), &parameterized::rx(PI / 2.0).to_matrix());
+        assert_close(&gpi2(0.25).to_matrix(), &square(2, &[h, -h, h, h]));
+        // MS(0, 0, 1/4) = exp(-iπ/4 X⊗X)
+        assert_close(
+            &ms(0.0, 0.0, 0.25).to_matrix(),
+            &square(4, &[h, o, o, -i * h, o, h, -i * h, o, o, -i * h, h, o, -i * h, o, o, h]),
+        );
+        assert_close(&ms(0.0, 0.0, 0.1).to_matrix(), &parameterized::rxx(0.2 * PI).to_matrix());
+        assert_close(&zz(0.1).to_matrix(), &parameterized::rzz(0.2 * PI).to_matrix());
+        assert_close(&gms(2, 0.4).to_matrix(), &parameterized::rxx(0.4).to_matrix());
+        assert_close(&ms(0.3, 0.1, 0.0).to_matrix(), &DMatrix::identity(4, 4));
+        assert_eq!(gms(3, 0.0).to_matrix().nrows(), 8);
+        assert_close(&gms(3, 0.0).to_matrix(), &DMatrix::identity(8, 8));
+    }
+
+    /// The global MS gate is a product of XX rotations over every pair
+    #[test]
+    fn test_global_ms() {
+        let theta: f64 = 0.7;
+        let xx = |i: usize, j: usize| {
+            let x = pauli_x();
+            let op = on_qubit(3, i, &x) * on_qubit(3, j, &x);
+            let (c, s) = (real((theta / 2.0).cos()), imag(-(theta / 2.0).sin()));
+            DMatrix::identity(8, 8) * c + op * s
+        };
+        assert_close(&gms(3, theta).to_matrix(), &(xx(0, 1) * xx(0, 2) * xx(1, 2)));
+    }
+
+    /// Evolving the drive Hamiltonian over the requested duration gives the
+    /// gate, up to GPI's global phase
+    #[test]
+    fn test_hamiltonians() {
+        let gates = [
+            gpi2(0.1),
+            parameterized::r(0.4, 1.2),
+            ms(0.1, 0.3, 0.25),
+            zz(0.15),
+            gms(3, 0.5),
+        ];
+        for gate in gates {
+            assert_eq!((gate.duration(), gate.hamiltonian()), (None, None));
+            let driven = with_hamiltonian(&gate, 20e-6).unwrap();
+            assert_eq!((driven.duration(), driven.unit()), (Some(20e-6), &TimeUnit::S));
+            assert_close(&evolve(&driven), &gate.to_matrix());
+        }
+        let gpi = with_hamiltonian(&gpi(0.2), 10e-6).unwrap();
+        assert_close(&(evolve(&gpi) * imag(1.0)), &gpi.to_matrix());
+        assert!(with_hamiltonian(&singleton::x(), 10e-6).is_none());
+    }
+
+    /// Binding symbolic parameters recomputes the matrix
+    #[test]
+    fn test_bind() {
+        let theta = crate::parameter::Parameter::new("theta".to_string());
+        let symbolic = from_params(
+            "zz",
+            2,
+            vec![Param::Expression(crate::parameter::ParameterExpression::Symbol(theta.clone()))],
+        )
+        .unwrap();
+        let bound = symbolic.bind(&std::collections::HashMap::from([(theta, 0.15)]));
+        assert_eq!(bound, zz(0.15));
+        assert!(from_params("ms", 2, vec![Param::Value(0.0); 2]).is_none());
+        assert!(definition("ms", MAX_GMS_QUBITS + 1, 1).is_some_and(|d| d.num_params == 3));
+    }
+
+    /// The global MS gate is limited to `MAX_GMS_QUBITS` qubits
+    #[test]
+    #[should_panic(expected = "2 to 10 qubits")]
+    fn test_gms_limit() {
+        gms(MAX_GMS_QUBITS + 1, 0.5);
+    }
+}
@@ -0,0 +1,352 @@
+use nalgebra::base::DMatrix;
+use crate::c64;
+
+use crate::operations::{Gate, GateBuilder, MatrixFn, TimeUnit};
+use crate::parameter::Param;
+
+use super::GateRegistry;
+
+/// Qiskit names of the parameterized gates with their number of qubits,
+/// number of parameters and matrix function.
+const DEFINITIONS: &[(&str, usize, usize, MatrixFn)] = &[
+    ("rx", 1, 1, rx_matrix),
+    ("ry", 1, 1, ry_matrix),
+    ("rz", 1, 1, rz_matrix),
+    ("p", 1, 1, p_matrix),
+    ("u", 1, 3, u_matrix),
+    ("u1", 1, 1, p_matrix),
+    ("u2", 1, 2, u2_matrix),
+    ("u3", 1, 3, u_matrix),
+    ("r", 1, 2, r_matrix),
+    ("rxx", 2, 1, rxx_matrix),
+    ("ryy", 2, 1, ryy_matrix),
+    ("rzz", 2, 1, rzz_matrix),
+    ("rzx", 2, 1, rzx_matrix),
+    ("cp", 2, 1, cp_matrix),
+    ("crx", 2, 1, crx_matrix),
+    ("cry", 2, 1, cry_matrix),
+    ("crz", 2, 1, crz_matrix),
+    ("cu", 2, 4, cu_matrix),
+];
+
+/// Number of qubits, number of parameters and matrix function of the
+/// parameterized gate with the given Qiskit name.
+pub fn definition(name: &str) -> Option<(usize, usize, MatrixFn)> {
+    DEFINITIONS
+        .iter()
+        .find(|(gate_name, ..)| *gate_name == name)
+        .map(|(_, num_qubits, num_params, matrix_fn)| (*num_qubits, *num_params, *matrix_fn))
+}
+
+/// Build the parameterized gate `name` from possibly symbolic parameters.
+/// Returns `None` if the name is unknown or the number of parameters is
+/// wrong. While parameters are symbolic the matrix is that of all-zero
+/// angles, standing in until the gate is bound.
+pub fn from_params(name: &str, params: Vec<Param>) -> Option<Gate> {
+    let (_, num_params, matrix_fn) = definition(name)?;
+    if params.len() != num_params {
+        return None;
+    }
+    let values: Vec<f64> = params.iter().map(|p| p.value().unwrap_or(0.0)).collect();
+    Some(
+        GateBuilder::new()
+            .name(name.to_string())
+            .params(params)
+            .unit(TimeUnit::DT)
+            .matrix(matrix_fn(&values))
+            .matrix_fn(matrix_fn)
+            .build(),
+    )
+}
+
+/// Register every gate of this module under its Qiskit name.
+pub fn register(registry: &mut GateRegistry) {
+    for (name, num_qubits, num_params, _) in DEFINITIONS {
+        registry.register(*name, *num_qubits, Some(*num_params), move |params: &[f64]| {
+            build(name, params)
+        });
+    }
+}
+
+fn build(name: &str, params: &[f64]) -> Gate {
+    from_params(name, params.iter().map(|p| Param::Value(*p)).collect())
+        .expect("parameterized gate definition")
+}
+
+pub fn rx(theta: f64) -> Gate {
+    build("rx", &[theta])
+}
+
+pub fn ry(theta: f64) -> Gate {
+    build("ry", &[theta])
+}
+
+pub fn rz(phi: f64) -> Gate {
+    build("rz", &[phi])
+}
+
+pub fn p(lambda: f64) -> Gate {
+    build("p", &[lambda])
+}
+
+pub fn u(theta: f64, phi: f64, lambda: f64) -> Gate {
+    build("u", &[theta, phi, lambda])
+}
+
+pub fn u1(lambda: f64) -> Gate {
+    build("u1", &[lambda])
+}
+
+pub fn u2(phi: f64, lambda: f64) -> Gate {
+    build("u2", &[phi, lambda])
+}
+
+pub fn u3(theta: f64, phi: f64, lambda: f64) -> Gate {
+    build("u3", &[theta, phi, lambda])
+}
+
+pub fn r(theta: f64, phi: f64) -> Gate {
+    build("r", &[theta, phi])
+}
+
+pub fn rxx(theta: f64) -> Gate {
+    build("rxx", &[theta])
+}
+
+pub fn ryy(theta: f64) -> Gate {
+    build("ryy", &[theta])
+}
+
+pub fn rzz(theta: f64) -> Gate {
+    build("rzz", &[theta])
+}
+
+pub fn rzx(theta: f64) -> Gate {
+    build("rzx", &[theta])
+}
+
+pub fn cp(lambda: f64) -> Gate {
+    build("cp", &[lambda])
+}
+
+pub fn crx(theta: f64) -> Gate {
+    build("crx", &[theta])
+}
+
+pub fn cry(theta: f64) -> Gate {
+    build("cry", &[theta])
+}
+
+pub fn crz(theta: f64) -> Gate {
+    build("crz", &[theta])
+}
+
+pub fn cu(theta: f64, phi: f64, lambda: f64, gamma: f64) -> Gate {
+    build("cu", &[theta, phi, lambda, gamma])
+}
+
+// Matrices follow Qiskit's little-endian convention: qubit 0 is the least
+// significant bit of the basis index, and is the control of controlled gates.
+
+fn real(x: f64) -> c64 {
+    c64::new(x, 0.0)
+}
+
+fn imag(x: f64) -> c64 {
+    c64::new(0.0, x)
+}
+
+fn cis(phi: f64) -> c64 {
+    c64::from_polar(1.0, phi)
+}
+
+fn square(dim: usize, rows: &[c64]) -> DMatrix<c64> {
+    DMatrix::from_row_slice(dim, dim, rows)
+}
+
+/// Embed a single-qubit matrix as the target of a gate controlled by qubit 0.
+fn controlled(target: DMatrix<c64>) -> DMatrix<c64> {
+    let mut matrix = DMatrix::identity(4, 4);
+    matrix[(1, 1)] = target[(0, 0)];
+    matrix[(1, 3)] = target[(0, 1)];
+    matrix[(3, 1)] = target[(1, 0)];
+    matrix[(3, 3)] = target[(1, 1)];
+    matrix
+}
+
+pub fn rx_matrix(params: &[f64]) -> DMatrix<c64> {
+    let (c, s) = ((params[0] / 2.0).cos(), (params[0] / 2.0).sin());
+    square(2, &[real(c), imag(-s), imag(-s), real(c)])
+}
+
+pub fn ry_matrix(params: &[f64]) -> DMatrix<c64> {
+    let (c, s) = ((params[0] / 2.0).cos(), (params[0] / 2.0).sin());
+    square(2, &[real(c), real(-s), real(s), real(c)])
+}
+
+pub fn rz_matrix(params: &[f64]) -> DMatrix<c64> {
+    let half = params[0] / 2.0;
+    square(2, &[cis(-half), real(0.0), real(0.0), cis(half)])
+}
+
+pub fn p_matrix(params: &[f64]) -> DMatrix<c64> {
+    square(2, &[real(1.0), real(0.0), real(0.0), cis(params[0])])
+}
+
+pub fn u_matrix(params: &[f64]) -> DMatrix<c64> {
+    let (theta, phi, lambda) = (params[0], params[1], params[2]);
+    let (c, s) = ((theta / 2.0).cos(), (theta / 2.0).sin());
+    square(
+        2,
+        &[
+            real(c),
+            -cis(lambda) * s,
+            cis(phi) * s,
+            cis(phi + lambda) * c,
+        ],
+    )
+}
+
+pub fn u2_matrix(params: &[f64]) -> DMatrix<c64> {
+    u_matrix(&[std::f64::consts::FRAC_PI_2, params[0], params[1]])
+}
+
+pub fn r_matrix(params: &[f64]) -> DMatrix<c64> {
+    let (theta, phi) = (params[0], params[1]);
+    let (c, s) = ((theta / 2.0).cos(), (theta / 2.0).sin());
+    square(
+        2,
+        &[
+            real(c),
+            imag(-1.0) * cis(-phi) * s,
+            imag(-1.0) * cis(phi) * s,
+            real(c),
+        ],
+    )
+}
+
+pub fn rxx_matrix(params: &[f64]) -> DMatrix<c64> {
+    let (c, s) = (real((params[0] / 2.0).cos()), imag(-(params[0] / 2.0).sin()));
+    let o = real(0.0);
+    square(4, &[c, o, o, s, o, c, s, o, o, s, c, o, s, o, o, c])
+}
+
+pub fn ryy_matrix(params: &[f64]) -> DMatrix<c64> {
+    let (c, s) = (real((params[0] / 2.0).cos()), imag((params[0] / 2.0).sin()));
+    let o = real(0.0);
+    square(4, &[c, o, o, s, o, c, -s, o, o, -s, c, o, s, o, o, c])
+}
+
+pub fn rzz_matrix(params: &[f64]) -> DMatrix<c64> {
+    let half = params[0] / 2.0;
+    let diagonal = [cis(-half), cis(half), cis(half), cis(-half)];
+    DMatrix::from_fn(4, 4, |i, j| if i == j { diagonal[i] } else { real(0.0) })
+}
+
+/// exp(-i θ/2 X⊗Z), with Z on qubit 0 and X on qubit 1.
+pub fn rzx_matrix(params: &[f64]) -> DMatrix<c64> {
+    let (c, s) = (real((params[0] / 2.0).cos()), imag((params[0] / 2.0).sin()));
+    let o = real(0.0);
+    square(4, &[c, o, -s, o, o, c, o, s, -s, o, c, o, o, s, o, c])
+}
+
+pub fn cp_matrix(params: &[f64]) -> DMatrix<c64> {
+    controlled(p_matrix(params))
+}
+
+pub fn crx_matrix(params: &[f64]) -> DMatrix<c64> {
+    controlled(rx_matrix(params))
+}
+
+pub fn cry_matrix(params: &[f64]) -> DMatrix<c64> {
+    controlled(ry_matrix(params))
+}
+
+pub fn crz_matrix(params: &[f64]) -> DMatrix<c64> {
+    controlled(rz_matrix(params))
+}
+
+/// Controlled U(θ, φ, λ) with an extra global phase γ on the target.
+pub fn cu_matrix(params: &[f64]) -> DMatrix<c64> {
+    controlled(u_matrix(&params[..3]) * cis(params[3]))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{FRAC_1_SQRT_2, PI};
+
+    use super::*;
+
+    fn assert_close(actual: &DMatrix<c64>, expected: &DMatrix<c64>) {
+        assert_eq!(actual.shape(), expected.shape());
+        assert!(
+            (actual - expected).norm() < 1e-12,
+            "matrices differ:\n{}\n{}",
+            actual,
+            expected
+        );
+    }
+
+    /// Values from Qiskit's `Operator` for the given angles
+    #[test]
+    fn test_single_qubit_rotations() {
+        let h = FRAC_1_SQRT_2;
+        assert_close(&rx(PI / 2.0).to_matrix(), &square(2, &[real(h), imag(-h), imag(-h), real(h)]));
+        assert_close(&ry(PI / 2.0).to_matrix(), &square(2, &[real(h), real(-h), real(h), real(h)]));
+        assert_close(
+            &rz(PI / 2.0).to_matrix(),
+            &square(2, &[c64::new(h, -h), real(0.0), real(0.0), c64::new(h, h)]),
+        );
+        assert_close(&p(PI).to_matrix(), &square(2, &[real(1.0), real(0.0), real(0.0), real(-1.0)]));
+        assert_close(&u1(0.3).to_matrix(), &p(0.3).to_matrix());
+        assert_close(&u3(0.1, 0.2, 0.3).to_matrix(), &u(0.1, 0.2, 0.3).to_matrix());
+        // U(π/2, 0, π) is the Hadamard
+        assert_close(&u2(0.0, PI).to_matrix(), &square(2, &[real(h), real(h), real(h), real(-h)]));
+        // R(θ, 0) is RX(θ) and R(θ, π/2) is RY(θ)
+        assert_close(&r(0.4, 0.0).to_matrix(), &rx(0.4).to_matrix());
+        assert_close(&r(0.4, PI / 2.0).to_matrix(), &ry(0.4).to_matrix());
+    }
+
+    /// Two-qubit gates use qubit 0 as the least significant bit
+    #[test]
+    fn test_two_qubit_rotations() {
+        let o = real(0.0);
+        let one = real(1.0);
+        // RZX(π) = -i X⊗Z
+        let i = imag(1.0);
+        assert_close(
+            &rzx(PI).to_matrix(),
+            &square(4, &[o, o, -i, o, o, o, o, i, -i, o, o, o, o, i, o, o]),
+        );
+        // CRX(π) applies -iX to qubit 1 when qubit 0 is set
+        assert_close(
+            &crx(PI).to_matrix(),
+            &square(4, &[one, o, o, o, o, o, o, -i, o, o, one, o, o, -i, o, o]),
+        );
+        // RZZ(π) = -i Z⊗Z
+        assert_close(
+            &rzz(PI).to_matrix(),
+            &square(4, &[-i, o, o, o, o, i, o, o, o, o, i, o, o, o, o, -i]),
+        );
+        // CP(π) is CZ
+        assert_close(
+            &cp(PI).to_matrix(),
+            &square(4, &[one, o, o, o, o, one, o, o, o, o, one, o, o, o, o, -one]),
+        );
+        assert_close(&cu(0.1, 0.2, 0.3, 0.0).to_matrix(), &controlled(u_matrix(&[0.1, 0.2, 0.3])));
+    }
+
+    /// Every gate is unitary, and the identity at zero and the identity at zero
+    #[test]
+    fn test_identity_at_zero() {
+        for (name, num_qubits, num_params, matrix_fn) in DEFINITIONS {
+            let dim = 1 << num_qubits;
+            let zero = matrix_fn(&vec![0.0; *num_params]);
+            if *name != "u2" {
+                assert_close(&zero, &DMatrix::identity(dim, dim));
+            }
+            let m = matrix_fn(&vec![0.3; *num_params]);
+            assert_close(&(m.adjoint() * &m), &DMatrix::identity(dim, dim));
+        }
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::operations::Gate;
+use crate::parameter::Param;
+
+use super::{ion, parameterized, singleton};
+
+/// Builds a gate from its numeric parameters.
+pub type GateConstructor = Arc<dyn Fn(&[f64]) -> Gate + Send + Sync>;
+
+/// How to build one gate: the number of qubits it acts on, the number of
+/// parameters it takes (`None` for any number) and its constructor.
+#[derive(Clone)]
+pub struct GateDefinition {
+    num_qubits: usize,
+    num_params: Option<usize>,
+    constructor: GateConstructor,
+}
+
+/// Maps gate names to their constructors. The parser resolves every
+/// instruction that is not a built-in operation through a registry.
+///
+/// [`GateRegistry::standard`] (also the default) holds Qiskit's standard gates
+/// and the trapped-ion native gates. A name may have several definitions that
+/// differ in their number of qubits or parameters, such as IonQ's two-qubit
+/// `ms(φ0, φ1, θ)` and Qiskit's global `ms(θ)`; later registrations take
+/// precedence over earlier ones with the same shape.
+#[derive(Clone, Default)]
+pub struct GateRegistry {
+    gates: HashMap<String, Vec<GateDefinition>>,
+}
+
+impl GateDefinition {
+    pub fn num_qubits(&self) -> usize {
+        self.num_qubits
+    }
+
+    pub fn num_params(&self) -> Option<usize> {
+        self.num_params
+    }
+
+    /// Whether the gate can be built from `num_params` parameters.
+    pub fn accepts(&self, num_params: usize) -> bool {
+        self.num_params.is_none_or(|expected| expected == num_params)
+    }
+
+    /// Build the gate from numeric parameters.
+    pub fn build(&self, params: &[f64]) -> Gate {
+        (self.constructor)(params)
+    }
+
+    /// Build the gate from possibly symbolic parameters. While any parameter
+    /// is symbolic the gate is built with zero in its place, and its matrix
+    /// is a placeholder until the gate is bound, when the constructor is
+    /// called again with the bound values.
+    pub fn instantiate(&self, params: Vec<Param>) -> Gate {
+        let values: Vec<f64> = params.iter().map(|p| p.value().unwrap_or(0.0)).collect();
+        let gate = self.build(&values);
+        if params.iter().all(|p| p.value().is_some()) {
+            return gate;
+        }
+        gate.with_constructor(self.constructor.clone()).with_params(params)
+    }
+}
+
+impl GateRegistry {
+    /// An empty registry.
+    pub fn new() -> Self {
+        GateRegistry {
+            gates: HashMap::new(),
+        }
+    }
+
+    /// A registry holding the standard library of gates.
+    pub fn standard() -> Self {
+        let mut registry = GateRegistry::new();
+        singleton::register(&mut registry);
+        parameterized::register(&mut registry);
+        ion::register(&mut registry);
+        registry
+    }
+
+    /// The registry with every gate taking `duration` seconds and, unless its
+    /// constructor sets a Hamiltonian, the generator of its matrix as its
+    /// Hamiltonian, see [`Gate::with_generator`].
+    pub fn with_generators(mut self, duration: f64) -> Self {
+        for definition in self.gates.values_mut().flatten() {
+            let constructor = definition.constructor.clone();
+            definition.constructor = Arc::new(move |params: &[f64]| {
+                let gate = constructor(params);
+                if gate.hamiltonian().is_some() {
+                    gate
+                } else {
+                    gate.with_generator(duration)
+                }
+            });
+        }
+        self
+    }
+
+    /// Register a constructor for the gate `name` acting on `num_qubits`
+    /// qubits, taking `num_params` parameters or any number if `None`.
+    pub fn register<F>(
+        &mut self,
+        name: impl Into<String>,
+        num_qubits: usize,
+        num_params: Option<usize>,
+        constructor: F,
+    ) where
+        F: Fn(&[f64]) -> Gate + Send + Sync + 'static,
+    {
+        let definitions = self.gates.entry(name.into()).or_default();
+        definitions.retain(|definition| {
+            definition.num_qubits != num_qubits || definition.num_params != num_params
+        });
+        definitions.insert(
+            0,
+            GateDefinition {
+                num_qubits,
+                num_params,
+                constructor: Arc::new(constructor),
+            },
+        );
+    }
+
+    /// Register `gate` under its own name. Instructions with that name get a
+    /// copy of the gate with their parameters, which recomputes the matrix if
+    /// the gate has a matrix function.
+    pub fn register_gate(&mut self, gate: Gate) {
+        let name = gate.name().clone();
+        let num_qubits = gate.num_qubits();
+        self.register(name, num_qubits, None, move |params: &[f64]| {
+            gate.with_params(params.iter().map(|p| Param::Value(*p)).collect())
+        });
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.gates.contains_key(name)
+    }
+
+    /// Names of the registered gates, sorted.
+    pub fn names(&self) -> Vec<&String> {
+        let mut names: Vec<&String> = self.gates.keys().collect();
+        names.sort();
+        names
+    }
+
+    /// The definition of `name` best matching an instruction on `num_qubits`
+    /// qubits with `num_params` parameters. Definitions accepting the
+    /// parameters are preferred, then those on the same number of qubits.
+    pub fn get(&self, name: &str, num_qubits: usize, num_params: usize) -> Option<&GateDefinition> {
+        let definitions = self.gates.get(name)?;
+        // `max_by_key` keeps the last of equal keys, so search from the back
+        // to prefer later registrations
+        definitions
+            .iter()
+            .rev()
+            .max_by_key(|definition| {
+                (
+                    definition.accepts(num_params),
+                    definition.num_qubits == num_qubits,
+                )
+            })
+    }
+
+    /// Build the gate `name` from numeric parameters, using the definition
+    /// for the gate's own number of qubits when there are several.
+    pub fn build(&self, name: &str, params: &[f64]) -> Option<Gate> {
+        let definitions = self.gates.get(name)?;
+        definitions
+            .iter()
+            .find(|definition| definition.accepts(params.len()))
+            .map(|definition| definition.build(params))
+    }
+}
+
+impl fmt::Debug for GateDefinition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GateDefinition")
+            .field("num_qubits", &self.num_qubits)
+            .field("num_params", &self.num_params)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for GateRegistry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_set().entries(self.names()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::approx::ApproxEq;
+    use crate::parameter::{Parameter, ParameterExpression};
+
+    /// The standard registry holds every library gate
+    #[test]
+    fn test_standard() {
+        let registry = GateRegistry::standard();
+        for name in ["h", "cx", "ecr", "mcx", "rx", "cu", "gpi2", "ms", "zz"] {
+            assert!(registry.contains(name), "missing {}", name);
+        }
+        assert_eq!(registry.build("rx", &[0.3]), Some(parameterized::rx(0.3)));
+        assert_eq!(registry.build("r", &[0.3, 0.1]), Some(parameterized::r(0.3, 0.1)));
+        assert_eq!(registry.build("h", &[]), Some(singleton::hadamard()));
+        assert_eq!(registry.build("mystery", &[]), None);
+    }
+
+    /// Definitions of the same name are told apart by their shape
+    #[test]
+    fn test_overloads() {
+        let registry = GateRegistry::standard();
+
+        let global = registry.get("ms", 3, 1).unwrap();
+        assert_eq!((global.num_qubits(), global.num_params()), (3, Some(1)));
+        assert_eq!(global.build(&[0.5]), ion::gms(3, 0.5));
+
+        let ionq = registry.get("ms", 2, 3).unwrap();
+        assert_eq!(ionq.build(&[0.0, 0.1, 0.25]), ion::ms(0.0, 0.1, 0.25));
+
+        // the closest definition is reported when none accepts the params
+        let wrong = registry.get("ms", 2, 2).unwrap();
+        assert!(!wrong.accepts(2));
+    }
+
+    /// User gates are added alongside, or in place of, library gates
+    #[test]
+    fn test_register() {
+        let mut registry = GateRegistry::standard();
+        registry.register("twice_rx", 1, Some(1), |params: &[f64]| {
+            parameterized::rx(2.0 * params[0])
+        });
+        registry.register_gate(singleton::x().builder().name("h".to_string()).build());
+
+        let twice = registry.get("twice_rx", 1, 1).unwrap();
+        assert_eq!(twice.build(&[0.2]).to_matrix(), parameterized::rx(0.4).to_matrix());
+        assert_eq!(
+            registry.build("h", &[]).unwrap().to_matrix(),
+            singleton::x().to_matrix()
+        );
+    }
+
+    /// Symbolic gates are rebuilt by their registered constructor on binding
+    #[test]
+    fn test_bind_constructor() {
+        let mut registry = GateRegistry::standard();
+        registry.register("twice_rx", 1, Some(1), |params: &[f64]| {
+            parameterized::rx(2.0 * params[0])
+        });
+        registry.register("rz_product", 1, Some(2), |params: &[f64]| {
+            parameterized::rz(params[0] * params[1])
+        });
+        let theta = Parameter::new("theta".to_string());
+        let param = Param::Expression(ParameterExpression::Symbol(theta.clone()));
+        let values = HashMap::from([(theta, 0.2)]);
+
+        let twice = registry.get("twice_rx", 1, 1).unwrap().instantiate(vec![param.clone()]);
+        assert_eq!(twice.params(), &vec![param.clone()]);
+        assert_eq!(twice.bind(&values).to_matrix(), parameterized::rx(0.4).to_matrix());
+        // and keep it when rebuilt through the builder
+        let rebuilt = twice.builder().build();
+        assert_eq!(rebuilt.bind(&values).to_matrix(), parameterized::rx(0.4).to_matrix());
+
+        // the wrapped gate takes fewer parameters than the constructor
+        let product = registry
+            .get("rz_product", 1, 2)
+            .unwrap()
+            .instantiate(vec![param, Param::Value(3.0)]);
+        assert!(product.bind(&values).matrix().approx_eq(parameterized::rz(0.6).matrix()));
+    }
+
+    /// Generators are added on request, and follow bound parameters
+    #[test]
+    fn test_with_generators() {
+        let registry = GateRegistry::standard().with_generators(50e-9);
+        let x = registry.build("x", &[]).unwrap();
+        assert_eq!(x.hamiltonian(), singleton::x().with_generator(50e-9).hamiltonian());
+        assert!(x.verify_hamiltonian(1e-9).is_ok());
+
+        let theta = Parameter::new("theta".to_string());
+        let param = Param::Expression(ParameterExpression::Symbol(theta.clone()));
+        let rx = registry.get("rx", 1, 1).unwrap().instantiate(vec![param]);
+        let bound = rx.bind(&HashMap::from([(theta, 0.3)]));
+        assert_eq!(bound.hamiltonian(), parameterized::rx(0.3).with_generator(50e-9).hamiltonian());
+        assert!(bound.verify_hamiltonian(1e-9).is_ok());
+
+        // Hamiltonians set by a constructor are kept
+        let mut registry = GateRegistry::new();
+        registry.register("drive", 1, Some(1), |params: &[f64]| {
+            ion::with_hamiltonian(&ion::gpi2(params[0]), 10e-6).unwrap()
+        });
+        let drive = registry.with_generators(50e-9).build("drive", &[0.1]).unwrap();
+        assert_eq!(drive.hamiltonian(), ion::with_hamiltonian(&ion::gpi2(0.1), 10e-6).unwrap().hamiltonian());
+    }
+
+    /// Symbolic parameters are kept, with a placeholder matrix
+    #[test]
+    fn test_instantiate() {
+        let registry = GateRegistry::standard();
+        let theta = Parameter::new("theta".to_string());
+        let param = Param::Expression(ParameterExpression::Symbol(theta.clone()));
+
+        let gate = registry.get("rz", 1, 1).unwrap().instantiate(vec![param.clone()]);
+        assert_eq!(gate.params(), &vec![param]);
+        assert_eq!(gate.to_matrix(), parameterized::rz(0.0).to_matrix());
+        assert_eq!(
+            gate.bind(&HashMap::from([(theta, 0.3)])),
+            parameterized::rz(0.3)
+        );
+    }
+}
@@ -1,93 +1,453 @@
 use nalgebra::base::DMatrix;
+use std::f64::consts::{FRAC_1_SQRT_2, FRAC_PI_4};
+
 use crate::c64;
 
-use crate::operations::{Gate, TimeUnit};
-
-macro_rules! call_all_functions {
-    ($($func:ident),*) => {
-        $(pub mod $func;)*
-    };
-}
-
-pub fn hadamard() -> Gate {
-    let factor = 1.0 / 2.0_f64.sqrt();
-    let matrix = DMatrix::from_vec(
-        2,
-        2,
-        vec![
-            c64::new(factor, 0.0),
-            c64::new(factor, 0.0),
-            c64::new(factor, 0.0),
-            c64::new(-factor, 0.0),
-        ],
-    );
-    Gate::new("h".to_string(), vec![], None, TimeUnit::DT, matrix, None)
-}
-
-pub fn x() -> Gate {
-    let matrix = DMatrix::from_vec(
-        2,
-        2,
-        vec![
-            c64::new(0.0, 0.0),
-            c64::new(1.0, 0.0),
-            c64::new(1.0, 0.0),
-            c64::new(0.0, 0.0),
-        ],
-    );
-    Gate::new("x".to_string(), vec![], None, TimeUnit::DT, matrix, None)
-}
-
-pub fn y() -> Gate {
-    let matrix = DMatrix::from_vec(
-        2,
-        2,
-        vec![
-            c64::new(0.0, 0.0),
-            c64::new(0.0, -1.0),
-            c64::new(0.0, 1.0),
-            c64::new(0.0, 0.0),
-        ],
-    );
-    Gate::new("y".to_string(), vec![], None, TimeUnit::DT, matrix, None)
-}
-
-pub fn z() -> Gate {
-    let matrix = DMatrix::from_vec(
-        2,
-        2,
-        vec![
-            c64::new(1.0, 0.0),
-            c64::new(0.0, 0.0),
-            c64::new(0.0, 0.0),
-            c64::new(-1.0, 0.0),
-        ],
-    );
-    Gate::new("z".to_string(), vec![], None, TimeUnit::DT, matrix, None)
-}
-
-pub fn cx() -> Gate {
-    let matrix = DMatrix::from_vec(
-        4,
-        4,
-        vec![
-            c64::new(1.0, 0.0),
-            c64::new(0.0, 0.0),
-            c64::new(0.0, 0.0),
-            c64::new(0.0, 0.0),
-            c64::new(0.0, 0.0),
-            c64::new(1.0, 0.0),
-            c64::new(0.0, 0.0),
-            c64::new(0.0, 0.0),
-            c64::new(0.0, 0.0),
-            c64::new(0.0, 0.0),
-            c64::new(0.0, 0.0),
-            c64::new(1.0, 0.0),
-            c64::new(0.0, 0.0),
-            c64::new(0.0, 0.0),
-            c64::new(1.0, 0.0),
-            c64::new(0.0, 0.0),
-        ],
-    );
-    Gate::new("cx".to_string(), vec![], None, TimeUnit::DT, matrix, None)
+use super::{gate, GateRegistry};
+
+/// Register every gate of this module under its Qiskit name.
+pub fn register(registry: &mut GateRegistry) {
+    let registrations = [
+        register_hadamard, register_x, register_y, register_z, register_cx, register_id,
+        register_s, register_sdg, register_t, register_tdg, register_sx, register_sxdg,
+        register_swap, register_iswap, register_cz, register_cy, register_ch, register_csx,
+        register_ecr, register_dcx, register_ccx, register_ccz, register_cswap, register_rccx,
+        register_rc3x, register_c3x, register_c3sx,
+    ];
+    for register in registrations {
+        register(registry);
+    }
+    // the name of Qiskit's `RC3XGate` class, used by some serializers
+    registry.register("rc3x", 4, Some(0), |_: &[f64]| rc3x());
+}
+
+#[gate(name = "h", qubits = 1)]
+pub fn hadamard() -> DMatrix<c64> {
+    let h = real(FRAC_1_SQRT_2);
+    square(2, &[h, h, h, -h])
+}
+
+#[gate(name = "x", qubits = 1)]
+pub fn x() -> DMatrix<c64> {
+    permutation(&[1, 0])
+}
+
+#[gate(name = "y", qubits = 1)]
+pub fn y() -> DMatrix<c64> {
+    square(2, &[real(0.0), imag(-1.0), imag(1.0), real(0.0)])
+}
+
+#[gate(name = "z", qubits = 1)]
+pub fn z() -> DMatrix<c64> {
+    diagonal(&[real(1.0), real(-1.0)])
+}
+
+#[gate(name = "cx", qubits = 2)]
+pub fn cx() -> DMatrix<c64> {
+    controlled(1, x_matrix(&[]))
+}
+
+#[gate(name = "id", qubits = 1)]
+pub fn id() -> DMatrix<c64> {
+    DMatrix::identity(2, 2)
+}
+
+#[gate(name = "s", qubits = 1)]
+pub fn s() -> DMatrix<c64> {
+    diagonal(&[real(1.0), imag(1.0)])
+}
+
+#[gate(name = "sdg", qubits = 1)]
+pub fn sdg() -> DMatrix<c64> {
+    diagonal(&[real(1.0), imag(-1.0)])
+}
+
+#[gate(name = "t", qubits = 1)]
+pub fn t() -> DMatrix<c64> {
+    diagonal(&[real(1.0), c64::from_polar(1.0, FRAC_PI_4)])
+}
+
+#[gate(name = "tdg", qubits = 1)]
+pub fn tdg() -> DMatrix<c64> {
+    diagonal(&[real(1.0), c64::from_polar(1.0, -FRAC_PI_4)])
+}
+
+#[gate(name = "sx", qubits = 1)]
+pub fn sx() -> DMatrix<c64> {
+    let (p, m) = (c64::new(0.5, 0.5), c64::new(0.5, -0.5));
+    square(2, &[p, m, m, p])
+}
+
+#[gate(name = "sxdg", qubits = 1)]
+pub fn sxdg() -> DMatrix<c64> {
+    sx_matrix(&[]).adjoint()
+}
+
+#[gate(name = "swap", qubits = 2)]
+pub fn swap() -> DMatrix<c64> {
+    permutation(&[0, 2, 1, 3])
+}
+
+#[gate(name = "iswap", qubits = 2)]
+pub fn iswap() -> DMatrix<c64> {
+    let (o, one, i) = (real(0.0), real(1.0), imag(1.0));
+    square(4, &[one, o, o, o, o, o, i, o, o, i, o, o, o, o, o, one])
+}
+
+#[gate(name = "cz", qubits = 2)]
+pub fn cz() -> DMatrix<c64> {
+    controlled(1, z_matrix(&[]))
+}
+
+#[gate(name = "cy", qubits = 2)]
+pub fn cy() -> DMatrix<c64> {
+    controlled(1, y_matrix(&[]))
+}
+
+#[gate(name = "ch", qubits = 2)]
+pub fn ch() -> DMatrix<c64> {
+    controlled(1, hadamard_matrix(&[]))
+}
+
+#[gate(name = "csx", qubits = 2)]
+pub fn csx() -> DMatrix<c64> {
+    controlled(1, sx_matrix(&[]))
+}
+
+/// Echoed cross-resonance gate, equivalent to CX up to single-qubit gates.
+#[gate(name = "ecr", qubits = 2)]
+pub fn ecr() -> DMatrix<c64> {
+    let (o, one, i) = (real(0.0), real(1.0), imag(1.0));
+    let matrix = square(4, &[o, one, o, i, one, o, -i, o, o, i, o, one, -i, o, one, o]);
+    matrix * real(FRAC_1_SQRT_2)
+}
+
+/// Double CNOT: CX from qubit 0 to 1 followed by CX from qubit 1 to 0.
+#[gate(name = "dcx", qubits = 2)]
+pub fn dcx() -> DMatrix<c64> {
+    permutation(&[0, 2, 3, 1])
+}
+
+#[gate(name = "ccx", qubits = 3)]
+pub fn ccx() -> DMatrix<c64> {
+    controlled(2, x_matrix(&[]))
+}
+
+#[gate(name = "ccz", qubits = 3)]
+pub fn ccz() -> DMatrix<c64> {
+    controlled(2, z_matrix(&[]))
+}
+
+/// Fredkin gate: qubit 0 controls a swap of qubits 1 and 2.
+#[gate(name = "cswap", qubits = 3)]
+pub fn cswap() -> DMatrix<c64> {
+    permutation(&[0, 1, 2, 5, 4, 3, 6, 7])
+}
+
+/// Toffoli up to relative phases (Margolus gate), cheaper to implement than
+/// `ccx`.
+#[gate(name = "rccx", qubits = 3)]
+pub fn rccx() -> DMatrix<c64> {
+    let mut matrix = DMatrix::identity(8, 8);
+    matrix[(3, 3)] = real(0.0);
+    matrix[(3, 7)] = imag(-1.0);
+    matrix[(5, 5)] = real(-1.0);
+    matrix[(7, 7)] = real(0.0);
+    matrix[(7, 3)] = imag(1.0);
+    matrix
+}
+
+/// Triply controlled X up to relative phases. Qiskit's `RC3XGate` is named
+/// `rcccx`.
+#[gate(name = "rcccx", qubits = 4)]
+pub fn rc3x() -> DMatrix<c64> {
+    let mut matrix = DMatrix::identity(16, 16);
+    matrix[(3, 3)] = imag(1.0);
+    matrix[(7, 7)] = real(0.0);
+    matrix[(7, 15)] = real(1.0);
+    matrix[(11, 11)] = imag(-1.0);
+    matrix[(15, 15)] = real(0.0);
+    matrix[(15, 7)] = real(-1.0);
+    matrix
+}
+
+/// Triply controlled X. Qiskit names this gate `mcx`, which is also used for
+/// multi-controlled X gates of other sizes.
+#[gate(name = "mcx", qubits = 4)]
+pub fn c3x() -> DMatrix<c64> {
+    controlled(3, x_matrix(&[]))
+}
+
+#[gate(name = "c3sx", qubits = 4)]
+pub fn c3sx() -> DMatrix<c64> {
+    controlled(3, sx_matrix(&[]))
+}
+
+// Matrices follow Qiskit's little-endian convention: qubit 0 is the least
+// significant bit of the basis index, and the controls of controlled gates
+// are the lowest qubits.
+
+fn real(x: f64) -> c64 {
+    c64::new(x, 0.0)
+}
+
+fn imag(x: f64) -> c64 {
+    c64::new(0.0, x)
+}
+
+fn square(dim: usize, rows: &[c64]) -> DMatrix<c64> {
+    DMatrix::from_row_slice(dim, dim, rows)
+}
+
+fn diagonal(entries: &[c64]) -> DMatrix<c64> {
+    DMatrix::from_fn(entries.len(), entries.len(), |i, j| {
+        if i == j {
+            entries[i]
+        } else {
+            real(0.0)
+        }
+    })
+}
+
+/// Matrix sending basis state `i` to basis state `image[i]`.
+fn permutation(image: &[usize]) -> DMatrix<c64> {
+    DMatrix::from_fn(image.len(), image.len(), |i, j| {
+        if image[j] == i {
+            real(1.0)
+        } else {
+            real(0.0)
+        }
+    })
+}
+
+/// Apply the single-qubit `target` to the qubit after the `num_controls`
+/// lowest ones when they are all set.
+fn controlled(num_controls: usize, target: DMatrix<c64>) -> DMatrix<c64> {
+    let dim = 1 << (num_controls + 1);
+    let mask = (1 << num_controls) - 1;
+    let (off, on) = (mask, mask | (1 << num_controls));
+    let mut matrix = DMatrix::identity(dim, dim);
+    matrix[(off, off)] = target[(0, 0)];
+    matrix[(off, on)] = target[(0, 1)];
+    matrix[(on, off)] = target[(1, 0)];
+    matrix[(on, on)] = target[(1, 1)];
+    matrix
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+    use crate::gates::parameterized;
+    use crate::operations::Gate;
+
+    fn assert_close(actual: &DMatrix<c64>, expected: &DMatrix<c64>) {
+        assert_eq!(actual.shape(), expected.shape());
+        assert!(
+            (actual - expected).norm() < 1e-12,
+            "matrices differ:\n{}\n{}",
+            actual,
+            expected
+        );
+    }
+
+    /// Embed `gate` acting on `qubits` (in operand order) into an
+    /// `num_qubits` circuit.
+    fn on(num_qubits: usize, gate: &Gate, qubits: &[usize]) -> DMatrix<c64> {
+        let matrix = gate.to_matrix();
+        let sub = |index: usize| {
+            qubits
+                .iter()
+                .enumerate()
+                .map(|(k, q)| ((index >> q) & 1) << k)
+                .sum::<usize>()
+        };
+        let mask: usize = qubits.iter().map(|q| 1 << q).sum();
+        let dim = 1 << num_qubits;
+        DMatrix::from_fn(dim, dim, |i, j| {
+            if i & !mask == j & !mask {
+                matrix[(sub(i), sub(j))]
+            } else {
+                real(0.0)
+            }
+        })
+    }
+
+    /// Compose a circuit of (gate, qubits) pairs, applied left to right.
+    fn circuit(num_qubits: usize, gates: &[(Gate, Vec<usize>)]) -> DMatrix<c64> {
+        let dim = 1 << num_qubits;
+        gates.iter().fold(DMatrix::identity(dim, dim), |acc, (gate, qubits)| {
+            on(num_qubits, gate, qubits) * acc
+        })
+    }
+
+    /// Values from Qiskit's `Operator`
+    #[test]
+    fn test_single_qubit_gates() {
+        let (o, one, i) = (real(0.0), real(1.0), imag(1.0));
+        let w = c64::new(FRAC_1_SQRT_2, FRAC_1_SQRT_2);
+        assert_close(&id().to_matrix(), &square(2, &[one, o, o, one]));
+        assert_close(&y().to_matrix(), &square(2, &[o, -i, i, o]));
+        assert_close(&s().to_matrix(), &square(2, &[one, o, o, i]));
+        assert_close(&sdg().to_matrix(), &square(2, &[one, o, o, -i]));
+        assert_close(&t().to_matrix(), &square(2, &[one, o, o, w]));
+        assert_close(&tdg().to_matrix(), &square(2, &[one, o, o, w.conj()]));
+        let (p, m) = (c64::new(0.5, 0.5), c64::new(0.5, -0.5));
+        assert_close(&sx().to_matrix(), &square(2, &[p, m, m, p]));
+        assert_close(&sxdg().to_matrix(), &square(2, &[m, p, p, m]));
+        assert_close(&(sx().to_matrix() * sx().to_matrix()), &x().to_matrix());
+    }
+
+    /// Values from Qiskit's `Operator`; qubit 0 is the control
+    #[test]
+    fn test_two_qubit_gates() {
+        let (o, one, i) = (real(0.0), real(1.0), imag(1.0));
+        let h = real(FRAC_1_SQRT_2);
+        let (p, m) = (c64::new(0.5, 0.5), c64::new(0.5, -0.5));
+        assert_close(
+            &cx().to_matrix(),
+            &square(4, &[one, o, o, o, o, o, o, one, o, o, one, o, o, one, o, o]),
+        );
+        assert_close(
+            &swap().to_matrix(),
+            &square(4, &[one, o, o, o, o, o, one, o, o, one, o, o, o, o, o, one]),
+        );
+        assert_close(
+            &iswap().to_matrix(),
+            &square(4, &[one, o, o, o, o, o, i, o, o, i, o, o, o, o, o, one]),
+        );
+        assert_close(
+            &cz().to_matrix(),
+            &square(4, &[one, o, o, o, o, one, o, o, o, o, one, o, o, o, o, -one]),
+        );
+        assert_close(
+            &cy().to_matrix(),
+            &square(4, &[one, o, o, o, o, o, o, -i, o, o, one, o, o, i, o, o]),
+        );
+        assert_close(
+            &ch().to_matrix(),
+            &square(4, &[one, o, o, o, o, h, o, h, o, o, one, o, o, h, o, -h]),
+        );
+        assert_close(
+            &csx().to_matrix(),
+            &square(4, &[one, o, o, o, o, p, o, m, o, o, one, o, o, m, o, p]),
+        );
+        assert_close(
+            &ecr().to_matrix(),
+            &square(4, &[o, h, o, i * h, h, o, -i * h, o, o, i * h, o, h, -i * h, o, h, o]),
+        );
+        assert_close(
+            &dcx().to_matrix(),
+            &square(4, &[one, o, o, o, o, o, o, one, o, one, o, o, o, o, one, o]),
+        );
+    }
+
+    /// Gates agree with their Qiskit definitions in terms of simpler gates
+    #[test]
+    fn test_definitions() {
+        assert_close(&ecr().to_matrix(), &circuit(2, &[
+            (parameterized::rzx(PI / 4.0), vec![0, 1]),
+            (x(), vec![0]),
+            (parameterized::rzx(-PI / 4.0), vec![0, 1]),
+        ]));
+        assert_close(&dcx().to_matrix(), &circuit(2, &[(cx(), vec![0, 1]), (cx(), vec![1, 0])]));
+        assert_close(&ccz().to_matrix(), &circuit(3, &[
+            (hadamard(), vec![2]),
+            (ccx(), vec![0, 1, 2]),
+            (hadamard(), vec![2]),
+        ]));
+        assert_close(&cswap().to_matrix(), &circuit(3, &[
+            (cx(), vec![2, 1]),
+            (ccx(), vec![0, 1, 2]),
+            (cx(), vec![2, 1]),
+        ]));
+
+        let u2 = parameterized::u2(0.0, PI);
+        let u1 = parameterized::u1;
+        assert_close(&rccx().to_matrix(), &circuit(3, &[
+            (u2.clone(), vec![2]),
+            (u1(PI / 4.0), vec![2]),
+            (cx(), vec![1, 2]),
+            (u1(-PI / 4.0), vec![2]),
+            (cx(), vec![0, 2]),
+            (u1(PI / 4.0), vec![2]),
+            (cx(), vec![1, 2]),
+            (u1(-PI / 4.0), vec![2]),
+            (u2.clone(), vec![2]),
+        ]));
+        assert_close(&rc3x().to_matrix(), &circuit(4, &[
+            (u2.clone(), vec![3]),
+            (u1(PI / 4.0), vec![3]),
+            (cx(), vec![2, 3]),
+            (u1(-PI / 4.0), vec![3]),
+            (u2.clone(), vec![3]),
+            (cx(), vec![0, 3]),
+            (u1(PI / 4.0), vec![3]),
+            (cx(), vec![1, 3]),
+            (u1(-PI / 4.0), vec![3]),
+            (cx(), vec![0, 3]),
+            (u1(PI / 4.0), vec![3]),
+            (cx(), vec![1, 3]),
+            (u1(-PI / 4.0), vec![3]),
+            (u2.clone(), vec![3]),
+            (u1(PI / 4.0), vec![3]),
+            (cx(), vec![2, 3]),
+            (u1(-PI / 4.0), vec![3]),
+            (u2, vec![3]),
+        ]));
+    }
+
+    /// Multi-controlled gates only act when every control is set
+    #[test]
+    fn test_multi_controlled_gates() {
+        let flip = |matrix: DMatrix<c64>, a: usize, b: usize| {
+            let dim = matrix.nrows();
+            let mut expected = DMatrix::identity(dim, dim);
+            expected[(a, a)] = real(0.0);
+            expected[(b, b)] = real(0.0);
+            expected[(a, b)] = real(1.0);
+            expected[(b, a)] = real(1.0);
+            assert_close(&matrix, &expected);
+        };
+        flip(ccx().to_matrix(), 3, 7);
+        flip(c3x().to_matrix(), 7, 15);
+        flip(cswap().to_matrix(), 3, 5);
+
+        let c3sx = c3sx().to_matrix();
+        assert_close(&(&c3sx * &c3sx), &c3x().to_matrix());
+        assert_eq!(c3sx[(6, 6)], real(1.0));
+        assert_eq!(c3sx[(7, 15)], c64::new(0.5, -0.5));
+    }
+
+    /// Gates are registered as fixed gates on their number of qubits
+    #[test]
+    fn test_register() {
+        let mut registry = GateRegistry::new();
+        register(&mut registry);
+
+        assert_eq!(registry.names().len(), 28);
+        assert_eq!(registry.build("rc3x", &[]), Some(rc3x()));
+        assert_eq!(rc3x().name(), "rcccx");
+        let mcx = registry.get("mcx", 4, 0).unwrap();
+        assert_eq!((mcx.num_qubits(), mcx.num_params()), (4, Some(0)));
+        assert_eq!(mcx.build(&[]), c3x());
+        assert!(!registry.get("h", 1, 1).unwrap().accepts(1));
+        assert_eq!(cswap_matrix(&[]), cswap().to_matrix());
+    }
+
+    /// Every gate is unitary
+    #[test]
+    fn test_unitary() {
+        let gates = [
+            hadamard(), x(), y(), z(), cx(), id(), s(), sdg(), t(), tdg(), sx(), sxdg(),
+            swap(), iswap(), cz(), cy(), ch(), csx(), ecr(), dcx(), ccx(), ccz(), cswap(),
+            rccx(), rc3x(), c3x(), c3sx(),
+        ];
+        for gate in gates {
+            let m = gate.to_matrix();
+            let dim = m.nrows();
+            assert_close(&(m.adjoint() * &m), &DMatrix::identity(dim, dim));
+        }
+    }
 }
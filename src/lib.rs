@@ -1,9 +1,17 @@
 use nalgebra::Complex;
 
+// lets `#[gate]` refer to this crate by name from inside it
+extern crate self as qiskit_parser;
+
+pub mod approx;
 pub mod circuit_instruction;
+pub mod error;
 pub mod operations;
+pub mod parameter;
 pub mod bit;
 pub mod gates;
 pub mod quantum_circuit;
+pub mod register;
 
+#[allow(non_camel_case_types)]
 pub type c64 = Complex<f64>;
\ No newline at end of file
@@ -1,79 +1,154 @@
 use nalgebra::base::DMatrix;
 use std::fmt::Debug;
+use std::collections::HashMap;
 use crate::c64;
+use crate::error::Mismatch;
+use crate::gates::GateConstructor;
+use crate::parameter::{Param, Parameter};
 
-pub type TimeDependentFn = fn(f64) -> c64;
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+mod algebra;
+mod evolution;
+mod time_dependence;
+
+pub use evolution::{Fidelity, PropagationMethod};
+pub use time_dependence::{CustomTimeFn, TimeDependence, TimeDependentFn};
+
+/// Computes a gate's matrix from its numeric parameters.
+pub type MatrixFn = fn(&[f64]) -> DMatrix<c64>;
+
+/// Computes a gate's Hamiltonian from its numeric parameters.
+pub type HamiltonianFn = fn(&[f64]) -> Hamiltonian;
+
+/// Operators commute when the norm of their commutator is at most this times
+/// the product of their norms.
+pub const COMMUTATION_TOLERANCE: f64 = 1e-10;
 
 /// Parts of a total Hamiltonian for a gate. This breaks up terms to easily
 /// determine commutativity and other properties.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct HamiltonianComponent {
-    time_fn: Option<TimeDependentFn>,
+    time_dependence: Option<TimeDependence>,
     constant: Option<c64>,
     operator: DMatrix<c64>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct Hamiltonian {
     components: Vec<HamiltonianComponent>,
 }
 
+/// Units of time used by Qiskit for durations. `DT` is the backend's sample
+/// time, whose length in seconds depends on the hardware.
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum TimeUnit {
     DT,
+    S,
+    MS,
+    US,
+    NS,
+    PS,
 }
 
 /// Contains all possible operations that can be applied to a quantum circuit.
-/// This includes gates, delays, barriers, and measurements.
+/// This includes gates, delays, barriers, measurements, and resets.
 #[derive(Debug, PartialEq, Clone)]
 pub enum Operation {
     Gate(Gate),
     Delay(Delay),
     Barrier(Barrier),
     Measurement(Measurement),
+    Reset(Reset),
+    Opaque(Opaque),
 }
 
 /// A quantum gate that can be applied to a quantum circuit
-#[derive(Debug, PartialEq, Clone)]
+///
+/// Parameterized gates carry a [`MatrixFn`], and optionally a
+/// [`HamiltonianFn`], so their matrix and Hamiltonian can be recomputed when
+/// parameters are bound. While any parameter is still symbolic both are only
+/// placeholders of the right dimension.
+///
+/// A gate may instead use the generator of its matrix as its Hamiltonian, see
+/// [`Gate::with_generator`], which is then recomputed along with the matrix.
+#[derive(Debug, Clone)]
 pub struct Gate {
     name: String,
-    params: Vec<f64>,
+    params: Vec<Param>,
     duration: Option<f64>,
     unit: TimeUnit,
     matrix: DMatrix<c64>,
+    matrix_fn: Option<MatrixFn>,
     hamiltonian: Option<Hamiltonian>,
+    hamiltonian_fn: Option<HamiltonianFn>,
+    generator: bool,
+    constructor: Option<Constructor>,
 }
 
+/// The registered constructor a symbolic gate was instantiated from, called
+/// again once its parameters are bound.
+#[derive(Clone)]
+struct Constructor(GateConstructor);
+
 /// GateBuilder enables custom gate creation
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, Clone)]
 pub struct GateBuilder {
     name: Option<String>,
-    params: Option<Vec<f64>>,
+    params: Option<Vec<Param>>,
     duration: Option<f64>,
     unit: Option<TimeUnit>,
     matrix: Option<DMatrix<c64>>,
+    matrix_fn: Option<MatrixFn>,
     hamiltonian: Option<Hamiltonian>,
+    hamiltonian_fn: Option<HamiltonianFn>,
+    generator: bool,
+    verify_tolerance: Option<f64>,
+    constructor: Option<Constructor>,
 }
 
+/// Idle time on a set of qubits
 #[derive(Debug, PartialEq, Clone)]
 pub struct Delay {
     duration: f64,
+    unit: TimeUnit,
 }
 
+/// Scheduling barrier across the given qubits
 #[derive(Debug, PartialEq, Clone)]
 pub struct Barrier {
-    // qubits: Vec<usize>,
+    qubits: Vec<usize>,
 }
 
+/// Measurement of qubits into classical bits
 #[derive(Debug, PartialEq, Clone)]
 pub struct Measurement {
-    // qubits: Vec<usize>,
+    mapping: Vec<(usize, usize)>,
+}
+
+/// Reset of qubits to the |0> state
+#[derive(Debug, PartialEq, Clone)]
+pub struct Reset {
+    qubits: Vec<usize>,
+}
+
+/// An instruction the parser does not know how to interpret, kept as it
+/// appeared in the repr
+#[derive(Debug, PartialEq, Clone)]
+pub struct Opaque {
+    name: String,
+    num_qubits: usize,
+    num_clbits: usize,
+    params: Vec<Param>,
 }
 
 impl Gate {
     pub fn new(
         name: String,
-        params: Vec<f64>,
+        params: Vec<Param>,
         duration: Option<f64>,
         unit: TimeUnit,
         matrix: DMatrix<c64>,
@@ -85,27 +160,37 @@ impl Gate {
             duration,
             unit,
             matrix,
+            matrix_fn: None,
             hamiltonian,
+            hamiltonian_fn: None,
+            generator: false,
+            constructor: None,
         }
     }
 
     /// If you want to update a prebuilt gate, utilize this method to update the
     /// gate and create a GateBuilder initialized with the gate's values.
     pub fn builder(&self) -> GateBuilder {
-        GateBuilder::new()
-            .name(self.name.clone())
-            .params(self.params.clone())
-            .duration(self.duration.unwrap())
-            .unit(self.unit)
-            .matrix(self.matrix.clone())
-            .hamiltonian(self.hamiltonian.clone().unwrap())
+        GateBuilder {
+            name: Some(self.name.clone()),
+            params: Some(self.params.clone()),
+            duration: self.duration,
+            unit: Some(self.unit),
+            matrix: Some(self.matrix.clone()),
+            matrix_fn: self.matrix_fn,
+            hamiltonian: self.hamiltonian.clone(),
+            hamiltonian_fn: self.hamiltonian_fn,
+            generator: self.generator,
+            verify_tolerance: None,
+            constructor: self.constructor.clone(),
+        }
     }
 
     pub fn name(&self) -> &String {
         &self.name
     }
 
-    pub fn params(&self) -> &Vec<f64> {
+    pub fn params(&self) -> &Vec<Param> {
         &self.params
     }
 
@@ -117,9 +202,128 @@ impl Gate {
         &self.unit
     }
 
+    /// Duration of the gate in seconds, if it has one. `dt` is only needed
+    /// for durations given in `DT`.
+    pub fn to_seconds(&self, dt: Option<f64>) -> Option<f64> {
+        self.duration
+            .and_then(|duration| self.unit.to_seconds(duration, dt))
+    }
+
+    /// Duration of the gate in samples of length `dt` seconds, if it has one.
+    pub fn to_dt(&self, dt: f64) -> Option<f64> {
+        self.duration.map(|duration| self.unit.to_dt(duration, dt))
+    }
+
     pub fn to_matrix(&self) -> DMatrix<c64> {
         self.matrix.clone()
     }
+
+    pub fn matrix(&self) -> &DMatrix<c64> {
+        &self.matrix
+    }
+
+    /// Number of qubits the gate acts on, given by the dimension of its
+    /// matrix.
+    pub fn num_qubits(&self) -> usize {
+        self.matrix.nrows().trailing_zeros() as usize
+    }
+
+    pub fn matrix_fn(&self) -> Option<MatrixFn> {
+        self.matrix_fn
+    }
+
+    pub fn hamiltonian(&self) -> Option<&Hamiltonian> {
+        self.hamiltonian.as_ref()
+    }
+
+    pub fn hamiltonian_fn(&self) -> Option<HamiltonianFn> {
+        self.hamiltonian_fn
+    }
+
+    /// Numeric values of the parameters, or `None` if any is still symbolic.
+    pub fn param_values(&self) -> Option<Vec<f64>> {
+        self.params.iter().map(Param::value).collect()
+    }
+
+    /// Whether every parameter has a numeric value.
+    pub fn is_bound(&self) -> bool {
+        self.param_values().is_some()
+    }
+
+    /// Return a copy of the gate with `params` in place of its own, updating
+    /// the matrix and Hamiltonian if the new parameters are all numeric.
+    /// Gates instantiated from a registered constructor are rebuilt by it.
+    pub fn with_params(&self, params: Vec<Param>) -> Gate {
+        let mut gate = self.clone();
+        gate.params = params;
+        if let Some(values) = gate.param_values() {
+            if let Some(Constructor(constructor)) = &self.constructor {
+                let mut rebuilt = constructor(&values);
+                rebuilt.constructor = self.constructor.clone();
+                if self.generator {
+                    rebuilt.duration = self.duration;
+                    rebuilt.unit = self.unit;
+                    rebuilt.generator = true;
+                    rebuilt.refresh_generator();
+                }
+                return rebuilt;
+            }
+            if let Some(matrix_fn) = gate.matrix_fn {
+                gate.matrix = matrix_fn(&values);
+            }
+            if let Some(hamiltonian_fn) = gate.hamiltonian_fn {
+                gate.hamiltonian = Some(hamiltonian_fn(&values));
+            }
+            gate.refresh_generator();
+        }
+        gate
+    }
+
+    /// Keep `constructor` to rebuild the gate once its parameters are bound.
+    pub(crate) fn with_constructor(mut self, constructor: GateConstructor) -> Gate {
+        self.constructor = Some(Constructor(constructor));
+        self
+    }
+
+    /// Substitute values for symbolic parameters, recomputing the matrix once
+    /// all parameters are bound.
+    pub fn bind(&self, values: &HashMap<Parameter, f64>) -> Gate {
+        self.with_params(self.params.iter().map(|param| param.bind(values)).collect())
+    }
+}
+
+impl Debug for Constructor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Constructor(..)")
+    }
+}
+
+/// Gates are compared by their data; the matrix and Hamiltonian functions are
+/// derived from the gate's definition and are not compared.
+impl PartialEq for Gate {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.params == other.params
+            && self.duration == other.duration
+            && self.unit == other.unit
+            && self.matrix == other.matrix
+            && self.hamiltonian == other.hamiltonian
+    }
+}
+
+/// Like gates, builders are compared without their matrix and Hamiltonian
+/// functions.
+impl PartialEq for GateBuilder {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+            && self.params == other.params
+            && self.duration == other.duration
+            && self.unit == other.unit
+            && self.matrix == other.matrix
+            && self.hamiltonian == other.hamiltonian
+            && self.generator == other.generator
+            && self.verify_tolerance == other.verify_tolerance
+    }
 }
 
 impl From<Operation> for Gate {
@@ -131,9 +335,145 @@ impl From<Operation> for Gate {
     }
 }
 
+impl TimeUnit {
+    /// Length of one unit in seconds, or `None` for `DT`.
+    pub fn seconds(&self) -> Option<f64> {
+        match self {
+            TimeUnit::DT => None,
+            TimeUnit::S => Some(1.0),
+            TimeUnit::MS => Some(1e-3),
+            TimeUnit::US => Some(1e-6),
+            TimeUnit::NS => Some(1e-9),
+            TimeUnit::PS => Some(1e-12),
+        }
+    }
+
+    /// Convert `duration` in this unit to seconds. `dt` is the length of a
+    /// sample in seconds, required only when the unit is `DT`.
+    pub fn to_seconds(&self, duration: f64, dt: Option<f64>) -> Option<f64> {
+        match self.seconds() {
+            Some(scale) => Some(duration * scale),
+            None => dt.map(|dt| duration * dt),
+        }
+    }
+
+    /// Convert `duration` in this unit to a number of samples of length `dt`
+    /// seconds.
+    pub fn to_dt(&self, duration: f64, dt: f64) -> f64 {
+        match self.seconds() {
+            Some(scale) => duration * scale / dt,
+            None => duration,
+        }
+    }
+}
+
+impl std::str::FromStr for TimeUnit {
+    type Err = String;
+
+    /// Parse a Qiskit unit string such as `'dt'` or `'us'`.
+    fn from_str(unit: &str) -> Result<Self, Self::Err> {
+        match unit {
+            "dt" => Ok(TimeUnit::DT),
+            "s" => Ok(TimeUnit::S),
+            "ms" => Ok(TimeUnit::MS),
+            "us" => Ok(TimeUnit::US),
+            "ns" => Ok(TimeUnit::NS),
+            "ps" => Ok(TimeUnit::PS),
+            _ => Err(format!("unknown time unit '{}'", unit)),
+        }
+    }
+}
+
 impl Delay {
-    pub fn new(duration: f64) -> Self {
-        Delay { duration }
+    pub fn new(duration: f64, unit: TimeUnit) -> Self {
+        Delay { duration, unit }
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.duration
+    }
+
+    pub fn unit(&self) -> &TimeUnit {
+        &self.unit
+    }
+
+    /// Duration of the delay in seconds. `dt` is only needed for delays
+    /// given in `DT`; `None` is returned if it is missing.
+    pub fn to_seconds(&self, dt: Option<f64>) -> Option<f64> {
+        self.unit.to_seconds(self.duration, dt)
+    }
+
+    /// Duration of the delay in samples of length `dt` seconds.
+    pub fn to_dt(&self, dt: f64) -> f64 {
+        self.unit.to_dt(self.duration, dt)
+    }
+}
+
+impl Barrier {
+    pub fn new(qubits: Vec<usize>) -> Self {
+        Barrier { qubits }
+    }
+
+    /// Circuit positions of the qubits the barrier spans.
+    pub fn qubits(&self) -> &Vec<usize> {
+        &self.qubits
+    }
+}
+
+impl Measurement {
+    pub fn new(mapping: Vec<(usize, usize)>) -> Self {
+        Measurement { mapping }
+    }
+
+    /// Pairs of (qubit, clbit) circuit positions, one per measured qubit.
+    pub fn mapping(&self) -> &Vec<(usize, usize)> {
+        &self.mapping
+    }
+}
+
+impl Reset {
+    pub fn new(qubits: Vec<usize>) -> Self {
+        Reset { qubits }
+    }
+
+    /// Circuit positions of the qubits being reset.
+    pub fn qubits(&self) -> &Vec<usize> {
+        &self.qubits
+    }
+}
+
+impl Opaque {
+    pub fn new(name: String, num_qubits: usize, num_clbits: usize, params: Vec<Param>) -> Self {
+        Opaque {
+            name,
+            num_qubits,
+            num_clbits,
+            params,
+        }
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn num_qubits(&self) -> usize {
+        self.num_qubits
+    }
+
+    pub fn num_clbits(&self) -> usize {
+        self.num_clbits
+    }
+
+    pub fn params(&self) -> &Vec<Param> {
+        &self.params
+    }
+
+    /// Substitute values for symbolic parameters.
+    pub fn bind(&self, values: &HashMap<Parameter, f64>) -> Opaque {
+        Opaque {
+            params: self.params.iter().map(|param| param.bind(values)).collect(),
+            ..self.clone()
+        }
     }
 }
 
@@ -143,6 +483,12 @@ impl Operation {
     }
 }
 
+impl Default for GateBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl GateBuilder {
     pub fn new() -> Self {
         GateBuilder {
@@ -151,7 +497,12 @@ impl GateBuilder {
             duration: None,
             unit: None,
             matrix: None,
+            matrix_fn: None,
             hamiltonian: None,
+            hamiltonian_fn: None,
+            generator: false,
+            verify_tolerance: None,
+            constructor: None,
         }
     }
 
@@ -160,7 +511,7 @@ impl GateBuilder {
         self
     }
 
-    pub fn params(mut self, params: Vec<f64>) -> Self {
+    pub fn params(mut self, params: Vec<Param>) -> Self {
         self.params = Some(params);
         self
     }
@@ -180,38 +531,106 @@ impl GateBuilder {
         self
     }
 
+    /// Set the function computing the matrix from numeric parameters. If no
+    /// matrix is given, it is computed from the parameters on build.
+    pub fn matrix_fn(mut self, matrix_fn: MatrixFn) -> Self {
+        self.matrix_fn = Some(matrix_fn);
+        self
+    }
+
     pub fn hamiltonian(mut self, hamiltonian: Hamiltonian) -> Self {
         self.hamiltonian = Some(hamiltonian);
+        self.generator = false;
         self
     }
 
+    /// Set the function computing the Hamiltonian from numeric parameters. If
+    /// no Hamiltonian is given, it is computed from the parameters on build
+    /// when they are all numeric.
+    pub fn hamiltonian_fn(mut self, hamiltonian_fn: HamiltonianFn) -> Self {
+        self.hamiltonian_fn = Some(hamiltonian_fn);
+        self.generator = false;
+        self
+    }
+
+    /// Use the generator of the matrix over the gate's duration as its
+    /// Hamiltonian, see [`Gate::with_generator`]. Without a duration in
+    /// physical units the gate has no Hamiltonian.
+    pub fn generator(mut self) -> Self {
+        self.hamiltonian = None;
+        self.hamiltonian_fn = None;
+        self.generator = true;
+        self
+    }
+
+    /// Check on build that the Hamiltonian generates the matrix, with
+    /// [`Gate::verify_hamiltonian`]. Off by default, as it evolves the
+    /// Hamiltonian.
+    pub fn verify_hamiltonian(mut self, tolerance: f64) -> Self {
+        self.verify_tolerance = Some(tolerance);
+        self
+    }
+
+    /// Build the gate, panicking if a requested Hamiltonian check fails.
     pub fn build(self) -> Gate {
-        Gate {
+        self.try_build().unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Build the gate, returning the [`Mismatch`] if a requested Hamiltonian
+    /// check fails.
+    pub fn try_build(self) -> Result<Gate, Mismatch> {
+        let params = self.params.unwrap();
+        let matrix = match (self.matrix, self.matrix_fn) {
+            (Some(matrix), _) => matrix,
+            (None, Some(matrix_fn)) => {
+                let values: Option<Vec<f64>> = params.iter().map(Param::value).collect();
+                matrix_fn(&values.expect("Gate matrix not set and params are not bound"))
+            }
+            (None, None) => panic!("Gate matrix not set"),
+        };
+        let hamiltonian = match (self.hamiltonian, self.hamiltonian_fn) {
+            (Some(hamiltonian), _) => Some(hamiltonian),
+            (None, Some(hamiltonian_fn)) => {
+                let values: Option<Vec<f64>> = params.iter().map(Param::value).collect();
+                values.map(|values| hamiltonian_fn(&values))
+            }
+            (None, None) => None,
+        };
+        let mut gate = Gate {
             name: self.name.expect("Gate name not set"),
-            params: self.params.unwrap(),
+            params,
             duration: self.duration,
             unit: self.unit.unwrap(),
-            matrix: self.matrix.expect("Gate matrix not set"),
-            hamiltonian: self.hamiltonian,
+            matrix,
+            matrix_fn: self.matrix_fn,
+            hamiltonian,
+            hamiltonian_fn: self.hamiltonian_fn,
+            generator: self.generator,
+            constructor: self.constructor,
+        };
+        gate.refresh_generator();
+        if let Some(tolerance) = self.verify_tolerance {
+            gate.verify_hamiltonian(tolerance)?;
         }
+        Ok(gate)
     }
 }
 
 impl HamiltonianComponent {
     pub fn new(
-        time_fn: TimeDependentFn,
+        time_dependence: TimeDependence,
         constant: c64,
         operator: DMatrix<c64>,
     ) -> Self {
         HamiltonianComponent {
-            time_fn: Some(time_fn),
+            time_dependence: Some(time_dependence),
             constant: Some(constant),
             operator,
         }
     }
 
-    pub fn time_fn(&self) -> &TimeDependentFn {
-        self.time_fn.as_ref().expect("Time function not set")
+    pub fn time_dependence(&self) -> &TimeDependence {
+        self.time_dependence.as_ref().expect("Time dependence not set")
     }
 
     pub fn constant(&self) -> &c64 {
@@ -222,11 +641,30 @@ impl HamiltonianComponent {
         &self.operator
     }
 
+    /// The commutator `[A, B] = AB - BA` of the two components' operators.
+    /// The time dependence and constant only scale it, so it vanishes at all
+    /// times exactly when the operators commute. `None` if the components
+    /// act on different numbers of qubits.
+    pub fn commutator(&self, other: &HamiltonianComponent) -> Option<DMatrix<c64>> {
+        if self.operator.shape() != other.operator.shape() {
+            return None;
+        }
+        Some(&self.operator * &other.operator - &other.operator * &self.operator)
+    }
+
+    /// Whether the two components commute, within [`COMMUTATION_TOLERANCE`].
+    /// Components acting on different numbers of qubits never do.
+    pub fn commutes_with(&self, other: &HamiltonianComponent) -> bool {
+        let scale = self.operator.norm() * other.operator.norm();
+        self.commutator(other)
+            .is_some_and(|commutator| commutator.norm() <= COMMUTATION_TOLERANCE * scale)
+    }
+
     pub fn calculate(&self, t: f64) -> DMatrix<c64> {
-        let time_fn = self.time_fn.expect("Time function not set");
+        let time_dependence = self.time_dependence();
         let constant = self.constant.expect("Constant not set");
 
-        let time_dep = time_fn(t);
+        let time_dep = time_dependence.at(t);
         let operator = self.operator.clone();
 
         operator * time_dep * constant
@@ -242,7 +680,106 @@ impl Hamiltonian {
         &self.components
     }
 
+    /// Whether all components commute pairwise, so the Hamiltonian commutes
+    /// with itself at different times and its time-ordered evolution is the
+    /// product of the exponentials of its integrated components.
     pub fn is_commutative(&self) -> bool {
-        todo!()
+        self.components.iter().enumerate().all(|(i, a)| {
+            self.components[i + 1..].iter().all(|b| a.commutes_with(b))
+        })
+    }
+
+    /// Whether every component commutes with every component of `other`, so
+    /// the evolutions under the two Hamiltonians can be applied in any order.
+    pub fn commutes_with(&self, other: &Hamiltonian) -> bool {
+        self.components
+            .iter()
+            .all(|a| other.components.iter().all(|b| a.commutes_with(b)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Qiskit unit strings map onto TimeUnit
+    #[test]
+    fn test_time_unit_from_str() {
+        assert_eq!("dt".parse(), Ok(TimeUnit::DT));
+        assert_eq!("us".parse(), Ok(TimeUnit::US));
+        assert_eq!("ps".parse(), Ok(TimeUnit::PS));
+        assert!("min".parse::<TimeUnit>().is_err());
+    }
+
+    /// Delays in different units land on a common timeline
+    #[test]
+    fn test_delay_conversion() {
+        let dt = 2e-9;
+        let samples = Delay::new(500.0, TimeUnit::DT);
+        let micros = Delay::new(1.0, TimeUnit::US);
+
+        assert_eq!(samples.to_seconds(None), None);
+        assert!((samples.to_seconds(Some(dt)).unwrap() - 1e-6).abs() < 1e-18);
+        assert!((micros.to_seconds(None).unwrap() - 1e-6).abs() < 1e-18);
+        assert!((micros.to_dt(dt) - samples.to_dt(dt)).abs() < 1e-9);
+    }
+
+    /// Gates without a duration have no position on the timeline
+    #[test]
+    fn test_gate_conversion() {
+        let matrix = DMatrix::identity(2, 2);
+        let timed = Gate::new("id".to_string(), vec![], Some(10.0), TimeUnit::NS, matrix.clone(), None);
+        let untimed = Gate::new("id".to_string(), vec![], None, TimeUnit::DT, matrix, None);
+
+        assert!((timed.to_dt(1e-9).unwrap() - 10.0).abs() < 1e-9);
+        assert_eq!(untimed.to_seconds(Some(1e-9)), None);
+        assert_eq!(untimed.to_dt(1e-9), None);
+    }
+
+    fn pauli(entries: [[f64; 2]; 2], imaginary: bool) -> DMatrix<c64> {
+        let scale = if imaginary { c64::new(0.0, 1.0) } else { c64::new(1.0, 0.0) };
+        DMatrix::from_fn(2, 2, |i, j| scale * entries[i][j])
+    }
+
+    fn component(operator: DMatrix<c64>) -> HamiltonianComponent {
+        HamiltonianComponent::new(TimeDependence::unit(), c64::new(2.0, 0.0), operator)
+    }
+
+    /// Components commute when their operators do, whatever their scale
+    #[test]
+    fn test_commutator() {
+        let x = component(pauli([[0.0, 1.0], [1.0, 0.0]], false));
+        let y = component(pauli([[0.0, -1.0], [1.0, 0.0]], true));
+        let z = component(pauli([[1.0, 0.0], [0.0, -1.0]], false));
+
+        // [X, Y] = 2iZ
+        let expected = z.operator() * c64::new(0.0, 2.0);
+        assert!((x.commutator(&y).unwrap() - expected).norm() < 1e-12);
+        assert!(x.commutes_with(&x));
+        assert!(!x.commutes_with(&z));
+
+        let rounded = component(x.operator() * c64::new(1e6, 0.0) + pauli([[1e-12, 0.0], [0.0, 0.0]], false));
+        assert!(x.commutes_with(&rounded));
+
+        // operators on different numbers of qubits have no commutator
+        let two_qubit = component(DMatrix::identity(4, 4));
+        assert!(x.commutator(&two_qubit).is_none());
+        assert!(!x.commutes_with(&two_qubit));
+    }
+
+    /// Hamiltonians are commutative when all their components commute
+    #[test]
+    fn test_is_commutative() {
+        let z = component(pauli([[1.0, 0.0], [0.0, -1.0]], false));
+        let identity = component(DMatrix::identity(2, 2));
+        let x = component(pauli([[0.0, 1.0], [1.0, 0.0]], false));
+
+        assert!(Hamiltonian::new(vec![]).is_commutative());
+        assert!(Hamiltonian::new(vec![z.clone(), identity.clone()]).is_commutative());
+        assert!(!Hamiltonian::new(vec![z.clone(), identity.clone(), x.clone()]).is_commutative());
+
+        let diagonal = Hamiltonian::new(vec![z.clone(), identity]);
+        assert!(diagonal.commutes_with(&Hamiltonian::new(vec![z])));
+        assert!(!diagonal.commutes_with(&Hamiltonian::new(vec![x])));
     }
 }
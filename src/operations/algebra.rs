@@ -0,0 +1,637 @@
+use std::f64::consts::{E, PI, SQRT_2};
+use std::sync::OnceLock;
+
+use nalgebra::base::{DMatrix, DVector};
+use crate::c64;
+
+use crate::approx::ApproxEq;
+use crate::gates::GateRegistry;
+use crate::parameter::Param;
+
+use super::{Gate, Hamiltonian, HamiltonianComponent};
+
+/// Library gates that are their own inverse.
+const SELF_INVERSE: &[&str] = &[
+    "h", "x", "y", "z", "id", "cx", "cy", "cz", "ch", "swap", "ecr", "ccx", "ccz", "cswap",
+    "rccx", "mcx",
+];
+
+/// Pairs of library gates that are each other's inverse.
+const INVERSE_PAIRS: &[(&str, &str)] = &[("s", "sdg"), ("t", "tdg"), ("sx", "sxdg")];
+
+/// Library gates with a single angle that scales with the power of the gate.
+const ROTATIONS: &[&str] = &[
+    "rx", "ry", "rz", "p", "u1", "rxx", "ryy", "rzz", "rzx", "cp", "crx", "cry", "crz", "zz",
+];
+
+/// Powers of fixed library gates that are other library gates.
+const POWERS: &[(&str, f64, &str)] = &[
+    ("x", 0.5, "sx"),
+    ("x", -0.5, "sxdg"),
+    ("z", 0.5, "s"),
+    ("z", -0.5, "sdg"),
+    ("z", 0.25, "t"),
+    ("z", -0.25, "tdg"),
+    ("s", 0.5, "t"),
+    ("s", -0.5, "tdg"),
+    ("sdg", 0.5, "tdg"),
+    ("s", 2.0, "z"),
+    ("sdg", 2.0, "z"),
+    ("t", 2.0, "s"),
+    ("tdg", 2.0, "sdg"),
+    ("sx", 2.0, "x"),
+    ("sxdg", 2.0, "x"),
+];
+
+/// Controlled versions of library gates, by number of controls.
+const CONTROLLED: &[(&str, usize, &str)] = &[
+    ("x", 1, "cx"),
+    ("x", 2, "ccx"),
+    ("x", 3, "mcx"),
+    ("cx", 1, "ccx"),
+    ("cx", 2, "mcx"),
+    ("ccx", 1, "mcx"),
+    ("y", 1, "cy"),
+    ("z", 1, "cz"),
+    ("z", 2, "ccz"),
+    ("cz", 1, "ccz"),
+    ("h", 1, "ch"),
+    ("sx", 1, "csx"),
+    ("sx", 3, "c3sx"),
+    ("csx", 2, "c3sx"),
+    ("swap", 1, "cswap"),
+    ("p", 1, "cp"),
+    ("rx", 1, "crx"),
+    ("ry", 1, "cry"),
+    ("rz", 1, "crz"),
+    ("u", 1, "cu"),
+];
+
+fn library() -> &'static GateRegistry {
+    static LIBRARY: OnceLock<GateRegistry> = OnceLock::new();
+    LIBRARY.get_or_init(GateRegistry::standard)
+}
+
+/// Whether `gate` is the library gate of the same name and parameters, as
+/// opposed to a user gate that reuses a library name for another matrix.
+fn matches_library(gate: &Gate) -> bool {
+    library()
+        .get(&gate.name, gate.num_qubits(), gate.params.len())
+        .filter(|definition| definition.accepts(gate.params.len()))
+        .is_some_and(|definition| {
+            definition.instantiate(gate.params.clone()).matrix().approx_eq(&gate.matrix)
+        })
+}
+
+/// Name and parameters of the inverse of a library gate, for gates whose
+/// inverse is again a library gate.
+fn library_inverse(name: &str, params: &[Param]) -> Option<(String, Vec<Param>)> {
+    if params.is_empty() {
+        if SELF_INVERSE.contains(&name) {
+            return Some((name.to_string(), vec![]));
+        }
+        return INVERSE_PAIRS.iter().find_map(|(a, b)| match name {
+            _ if name == *a => Some((b.to_string(), vec![])),
+            _ if name == *b => Some((a.to_string(), vec![])),
+            _ => None,
+        });
+    }
+    let p = params.to_vec();
+    let inverse = match (name, p.as_slice()) {
+        (_, [theta]) if ROTATIONS.contains(&name) => vec![-theta.clone()],
+        ("gpi", [_]) => p.clone(),
+        ("gpi2", [phi]) => vec![phi.clone() + 0.5],
+        // the global MS gate
+        ("ms", [theta]) => vec![-theta.clone()],
+        ("ms", [phi0, phi1, theta]) => vec![phi0.clone() + 0.5, phi1.clone(), theta.clone()],
+        ("r", [theta, phi]) => vec![-theta.clone(), phi.clone()],
+        ("u" | "u3", [theta, phi, lambda]) => {
+            vec![-theta.clone(), -lambda.clone(), -phi.clone()]
+        }
+        ("u2", [phi, lambda]) => vec![-lambda.clone() + -PI, -phi.clone() + PI],
+        ("cu", [theta, phi, lambda, gamma]) => vec![
+            -theta.clone(),
+            -lambda.clone(),
+            -phi.clone(),
+            -gamma.clone(),
+        ],
+        _ => return None,
+    };
+    Some((name.to_string(), inverse))
+}
+
+/// Parameters of a library gate raised to `exponent`, for gates whose powers
+/// are the same gate with scaled parameters.
+fn library_power(name: &str, params: &[Param], exponent: f64) -> Option<Vec<Param>> {
+    let p = params.to_vec();
+    match (name, p.as_slice()) {
+        (_, [theta]) if ROTATIONS.contains(&name) => Some(vec![theta.clone() * exponent]),
+        ("ms", [theta]) => Some(vec![theta.clone() * exponent]),
+        ("ms", [phi0, phi1, theta]) => {
+            Some(vec![phi0.clone(), phi1.clone(), theta.clone() * exponent])
+        }
+        ("r", [theta, phi]) => Some(vec![theta.clone() * exponent, phi.clone()]),
+        _ => None,
+    }
+}
+
+/// Mixing weights tried by [`unitary_eigen`], irrational so that distinct
+/// eigenvalues of common gates stay distinct in the mixture.
+const EIGEN_MIXTURES: &[f64] = &[0.618_033_988_7, -SQRT_2, E];
+
+/// Largest error, in Frobenius norm, of an accepted eigendecomposition.
+const EIGEN_TOLERANCE: f64 = 1e-10;
+
+/// Eigenvectors and eigenvalues of a unitary `U`.
+///
+/// The Hermitian and anti-Hermitian parts of `U` commute, so a real mixture
+/// of them shares its eigenvectors, which the Hermitian eigensolver finds
+/// reliably; nalgebra's Schur decomposition does not converge on some
+/// permutation matrices. Mixtures are tried until one reproduces `U`;
+/// `None` is returned if none does, e.g. because `U` is not unitary.
+fn unitary_eigen(matrix: &DMatrix<c64>) -> Option<(DMatrix<c64>, DVector<c64>)> {
+    let real_part = (matrix + matrix.adjoint()) * c64::new(0.5, 0.0);
+    let imaginary_part = (matrix - matrix.adjoint()) * c64::new(0.0, -0.5);
+    EIGEN_MIXTURES.iter().find_map(|weight| {
+        let mixture = &real_part + &imaginary_part * c64::new(*weight, 0.0);
+        let q = mixture.symmetric_eigen().eigenvectors;
+        let eigenvalues = (q.adjoint() * matrix * &q).diagonal();
+        let error = (&q * DMatrix::from_diagonal(&eigenvalues) * q.adjoint() - matrix).norm();
+        (error < EIGEN_TOLERANCE).then_some((q, eigenvalues))
+    })
+}
+
+/// Argument in (-π, π]. Eigenvalues on the negative real axis are kept on
+/// the +π branch, so that X^0.5 is SX rather than SXdg.
+fn principal_arg(eigenvalue: c64) -> f64 {
+    let arg = eigenvalue.arg();
+    if arg < -PI + 1e-9 {
+        PI
+    } else {
+        arg
+    }
+}
+
+/// Principal `exponent`-th power of a unitary, if it can be diagonalized.
+fn unitary_power(matrix: &DMatrix<c64>, exponent: f64) -> Option<DMatrix<c64>> {
+    let (q, eigenvalues) = unitary_eigen(matrix)?;
+    let powers = eigenvalues.map(|eigenvalue| {
+        c64::from_polar(eigenvalue.norm().powf(exponent), exponent * principal_arg(eigenvalue))
+    });
+    Some(&q * DMatrix::from_diagonal(&powers) * q.adjoint())
+}
+
+/// The Hermitian `G` with `U = exp(-iG)` and eigenvalues in [-π, π), from
+/// the principal logarithm of the unitary `U`, if it can be diagonalized.
+pub(super) fn principal_generator(matrix: &DMatrix<c64>) -> Option<DMatrix<c64>> {
+    let (q, eigenvalues) = unitary_eigen(matrix)?;
+    let phases = eigenvalues.map(|eigenvalue| c64::new(-principal_arg(eigenvalue), 0.0));
+    let generator = &q * DMatrix::from_diagonal(&phases) * q.adjoint();
+    // remove the rounding that leaves it slightly non-Hermitian
+    Some((&generator + generator.adjoint()) * c64::new(0.5, 0.0))
+}
+
+/// Projector onto `ctrl_state` of `num_ctrl` control qubits.
+fn control_projector(num_ctrl: usize, ctrl_state: usize) -> DMatrix<c64> {
+    let dim = 1 << num_ctrl;
+    let mut projector = DMatrix::zeros(dim, dim);
+    projector[(ctrl_state, ctrl_state)] = c64::new(1.0, 0.0);
+    projector
+}
+
+fn format_exponent(exponent: f64) -> String {
+    if exponent.fract() == 0.0 {
+        format!("{}", exponent as i64)
+    } else {
+        format!("{}", exponent)
+    }
+}
+
+impl Gate {
+    /// The inverse of the gate, with the conjugate transpose of its matrix.
+    ///
+    /// Library gates whose inverse is again a library gate keep their
+    /// definition, e.g. `s` becomes `sdg` and `rx(θ)` becomes `rx(-θ)`, so
+    /// symbolic parameters stay bindable, provided the gate's matrix is the
+    /// library one. Other gates get `_dg` appended to their name (or removed
+    /// from it) and a negated Hamiltonian.
+    ///
+    /// Panics if the gate has unbound parameters and no library inverse.
+    pub fn inverse(&self) -> Gate {
+        let rule = library_inverse(&self.name, &self.params)
+            .filter(|_| self.params.is_empty() || self.matrix_fn.is_some())
+            .filter(|_| matches_library(self));
+        let Some((name, params)) = rule else {
+            self.assert_bound("invert");
+            let name = match self.name.strip_suffix("_dg") {
+                Some(base) => base.to_string(),
+                None => format!("{}_dg", self.name),
+            };
+            let mut gate = Gate {
+                name,
+                matrix: self.matrix.adjoint(),
+                matrix_fn: None,
+                hamiltonian: self.hamiltonian.as_ref().map(|h| h.scaled(-1.0)),
+                hamiltonian_fn: None,
+                constructor: None,
+                ..self.clone()
+            };
+            gate.refresh_generator();
+            return gate;
+        };
+
+        let mut gate = self.clone();
+        gate.constructor = None;
+        gate.name = name;
+        gate.params = params;
+        let values = gate.param_values();
+        match (gate.matrix_fn, &values) {
+            (Some(matrix_fn), Some(values)) => gate.matrix = matrix_fn(values),
+            (None, _) => gate.matrix = self.matrix.adjoint(),
+            // the placeholder is recomputed once the parameters are bound
+            (Some(_), None) => {}
+        }
+        match (gate.hamiltonian_fn, &values) {
+            (Some(hamiltonian_fn), Some(values)) => gate.hamiltonian = Some(hamiltonian_fn(values)),
+            (Some(_), None) => {}
+            (None, _) => gate.hamiltonian = self.hamiltonian.as_ref().map(|h| h.scaled(-1.0)),
+        }
+        if values.is_some() {
+            gate.refresh_generator();
+        }
+        gate
+    }
+
+    /// The gate raised to a real `exponent`, using the principal branch.
+    ///
+    /// Rotations scale their angle, like Qiskit, and keep their definition;
+    /// powers of fixed library gates that are library gates are named after
+    /// them, e.g. `x^0.5` is `sx`, provided the gate's matrix is the library
+    /// one. Other gates are named `name^exponent`, with the matrix computed
+    /// from the eigendecomposition and the Hamiltonian scaled by `exponent`.
+    ///
+    /// Panics if the gate has unbound parameters and is not a rotation, or if
+    /// its matrix is not unitary.
+    pub fn power(&self, exponent: f64) -> Gate {
+        if exponent == 1.0 {
+            return self.clone();
+        }
+        if self.matrix_fn.is_some() && matches_library(self) {
+            if let Some(params) = library_power(&self.name, &self.params, exponent) {
+                let gate = Gate {
+                    constructor: None,
+                    ..self.clone()
+                };
+                return gate.with_params(params);
+            }
+        }
+        self.assert_bound("raise to a power");
+
+        let mut gate = Gate {
+            matrix: unitary_power(&self.matrix, exponent).unwrap_or_else(|| {
+                panic!("cannot raise gate '{}' to a power: its matrix is not unitary", self.name)
+            }),
+            matrix_fn: None,
+            hamiltonian: self.hamiltonian.as_ref().map(|h| h.scaled(exponent)),
+            hamiltonian_fn: None,
+            constructor: None,
+            ..self.clone()
+        };
+        let library_name = POWERS
+            .iter()
+            .find(|(base, power, _)| *base == self.name && *power == exponent && self.params.is_empty())
+            .filter(|_| matches_library(self))
+            .map(|(_, _, name)| name.to_string());
+        gate.name = match library_name {
+            Some(name) => name,
+            None if exponent == -1.0 => return self.inverse(),
+            None => format!("{}^{}", self.name, format_exponent(exponent)),
+        };
+        gate.refresh_generator();
+        gate
+    }
+
+    /// The gate controlled by `num_ctrl` new qubits, which become the lowest
+    /// qubits of the result. The gate acts when the controls are in
+    /// `ctrl_state`, by default all ones.
+    ///
+    /// Controlled library gates that are library gates keep their definition,
+    /// e.g. `x` with three controls is `mcx`, provided the gate's matrix is
+    /// the library one. Other gates are named `c{name}`,
+    /// or `c{n}{name}` for several controls, with `_o{state}` appended for a
+    /// control state other than all ones. The Hamiltonian acts only on the
+    /// control state, so a global phase of the gate's evolution becomes a
+    /// relative phase. Library gates with their default Hamiltonian map onto
+    /// library gates with theirs.
+    ///
+    /// Panics if `ctrl_state` does not fit in `num_ctrl` bits, or if the gate
+    /// has unbound parameters and no library controlled version.
+    pub fn control(&self, num_ctrl: usize, ctrl_state: Option<usize>) -> Gate {
+        let all_ones = (1 << num_ctrl) - 1;
+        let ctrl_state = ctrl_state.unwrap_or(all_ones);
+        assert!(
+            ctrl_state <= all_ones,
+            "control state {} does not fit in {} qubit(s)",
+            ctrl_state,
+            num_ctrl
+        );
+        if num_ctrl == 0 {
+            return self.clone();
+        }
+
+        let num_qubits = self.num_qubits() + num_ctrl;
+        let hamiltonian = self
+            .hamiltonian
+            .as_ref()
+            .map(|h| h.controlled(num_ctrl, ctrl_state));
+
+        let projector = control_projector(num_ctrl, ctrl_state);
+        let identity = DMatrix::identity(self.matrix.nrows(), self.matrix.nrows());
+        let rest = DMatrix::identity(1 << num_ctrl, 1 << num_ctrl) - &projector;
+        let matrix = self.matrix.kronecker(&projector) + identity.kronecker(&rest);
+
+        let library_name = CONTROLLED
+            .iter()
+            .find(|(base, n, _)| *base == self.name && *n == num_ctrl)
+            .map(|(_, _, name)| *name)
+            .filter(|_| ctrl_state == all_ones);
+        if let Some(name) = library_name {
+            let mut params = self.params.clone();
+            if name == "cu" {
+                // Qiskit's CU carries an extra global phase of the target
+                params.push(Param::Value(0.0));
+            }
+            let library_gate = library()
+                .get(name, num_qubits, params.len())
+                .map(|definition| definition.instantiate(params))
+                // a user gate may reuse a library name for another matrix
+                .filter(|gate| gate.matrix().approx_eq(&matrix));
+            if let Some(mut gate) = library_gate {
+                gate.duration = self.duration;
+                gate.unit = self.unit;
+                gate.hamiltonian = hamiltonian;
+                gate.generator = self.generator;
+                gate.refresh_generator();
+                return gate;
+            }
+        }
+        self.assert_bound("control");
+
+        let mut name = match num_ctrl {
+            1 => format!("c{}", self.name),
+            n => format!("c{}{}", n, self.name),
+        };
+        if ctrl_state != all_ones {
+            name = format!("{}_o{}", name, ctrl_state);
+        }
+        let mut gate = Gate {
+            name,
+            matrix,
+            matrix_fn: None,
+            hamiltonian,
+            hamiltonian_fn: None,
+            constructor: None,
+            ..self.clone()
+        };
+        gate.refresh_generator();
+        gate
+    }
+
+    fn assert_bound(&self, action: &str) {
+        assert!(
+            self.is_bound(),
+            "cannot {} gate '{}' with unbound parameters",
+            action,
+            self.name
+        );
+    }
+}
+
+impl Hamiltonian {
+    /// The Hamiltonian with every component multiplied by `factor`.
+    pub fn scaled(&self, factor: f64) -> Hamiltonian {
+        Hamiltonian::new(
+            self.components
+                .iter()
+                .map(|component| HamiltonianComponent {
+                    constant: component.constant.map(|constant| constant * factor),
+                    ..component.clone()
+                })
+                .collect(),
+        )
+    }
+
+    /// The Hamiltonian acting only when `num_ctrl` new control qubits, placed
+    /// below the existing ones, are in `ctrl_state`.
+    pub fn controlled(&self, num_ctrl: usize, ctrl_state: usize) -> Hamiltonian {
+        let projector = control_projector(num_ctrl, ctrl_state);
+        Hamiltonian::new(
+            self.components
+                .iter()
+                .map(|component| HamiltonianComponent {
+                    operator: component.operator.kronecker(&projector),
+                    ..component.clone()
+                })
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::f64::consts::FRAC_PI_2;
+
+    use super::*;
+    use crate::gates::{ion, parameterized, singleton};
+    use crate::parameter::{Parameter, ParameterExpression};
+
+    fn assert_close(actual: &DMatrix<c64>, expected: &DMatrix<c64>) {
+        assert_eq!(actual.shape(), expected.shape());
+        assert!(
+            (actual - expected).norm() < 1e-9,
+            "matrices differ:\n{}\n{}",
+            actual,
+            expected
+        );
+    }
+
+    fn symbol(name: &str) -> Param {
+        Param::Expression(ParameterExpression::Symbol(Parameter::new(name.to_string())))
+    }
+
+    /// Inverses are named like Qiskit's and undo the gate
+    #[test]
+    fn test_inverse() {
+        assert_eq!(singleton::s().inverse(), singleton::sdg());
+        assert_eq!(singleton::tdg().inverse(), singleton::t());
+        assert_eq!(singleton::hadamard().inverse(), singleton::hadamard());
+        assert_eq!(parameterized::rx(0.3).inverse(), parameterized::rx(-0.3));
+        assert_eq!(parameterized::u(0.1, 0.2, 0.3).inverse(), parameterized::u(-0.1, -0.3, -0.2));
+        assert_close(
+            &parameterized::u2(0.2, 0.3).inverse().to_matrix(),
+            &parameterized::u2(0.2, 0.3).to_matrix().adjoint(),
+        );
+        assert_close(
+            &ion::ms(0.1, 0.2, 0.25).inverse().to_matrix(),
+            &ion::ms(0.1, 0.2, 0.25).to_matrix().adjoint(),
+        );
+
+        let iswap = singleton::iswap();
+        let iswap_dg = iswap.inverse();
+        assert_eq!(iswap_dg.name(), "iswap_dg");
+        assert_close(&(iswap_dg.to_matrix() * iswap.to_matrix()), &DMatrix::identity(4, 4));
+        assert_eq!(iswap_dg.inverse(), iswap);
+    }
+
+    /// Symbolic library gates stay symbolic and bindable
+    #[test]
+    fn test_symbolic() {
+        let theta = Parameter::new("theta".to_string());
+        let values = HashMap::from([(theta, 0.4)]);
+        let rz = parameterized::rz(0.0).with_params(vec![symbol("theta")]);
+
+        assert_eq!(rz.inverse().bind(&values), parameterized::rz(-0.4));
+        assert_eq!(rz.power(0.5).bind(&values).to_matrix(), parameterized::rz(0.2).to_matrix());
+        assert_eq!(rz.control(1, None).bind(&values), parameterized::crz(0.4));
+    }
+
+    /// Hamiltonians are negated, scaled or conditioned on the controls
+    #[test]
+    fn test_hamiltonians() {
+        let drive = |gate: Gate| ion::with_hamiltonian(&gate, 10e-6).unwrap();
+        let zz = drive(ion::zz(0.1));
+        assert_eq!(zz.inverse().hamiltonian(), drive(ion::zz(-0.1)).hamiltonian());
+
+        let gpi2 = drive(ion::gpi2(0.1));
+        let custom = gpi2.builder().name("drive".to_string()).build();
+        let inverse = custom.inverse();
+        let component = &inverse.hamiltonian().unwrap().components()[0];
+        assert_eq!(
+            *component.constant(),
+            -gpi2.hamiltonian().unwrap().components()[0].constant()
+        );
+        let root = custom.power(0.5);
+        let component = &root.hamiltonian().unwrap().components()[0];
+        assert_eq!(
+            *component.constant(),
+            gpi2.hamiltonian().unwrap().components()[0].constant() * 0.5
+        );
+
+        let controlled = custom.control(1, Some(0));
+        assert_eq!(controlled.name(), "cdrive_o0");
+        let operator = controlled.hamiltonian().unwrap().components()[0].operator();
+        assert_eq!(operator.shape(), (4, 4));
+        // the control, qubit 0, must be |0>
+        assert_eq!(operator.column(1).norm(), 0.0);
+        assert_eq!(operator.column(3).norm(), 0.0);
+    }
+
+    /// Powers follow the principal branch and map onto library gates
+    #[test]
+    fn test_power() {
+        assert_eq!(singleton::x().power(0.5).name(), "sx");
+        assert_close(&singleton::x().power(0.5).to_matrix(), &singleton::sx().to_matrix());
+        assert_close(&singleton::x().power(-0.5).to_matrix(), &singleton::sxdg().to_matrix());
+        assert_close(&singleton::z().power(0.25).to_matrix(), &singleton::t().to_matrix());
+        assert_close(&singleton::s().power(2.0).to_matrix(), &singleton::z().to_matrix());
+        assert_eq!(singleton::s().power(-1.0), singleton::sdg());
+        assert_eq!(parameterized::rzz(0.4).power(2.0), parameterized::rzz(0.8));
+        assert_eq!(ion::ms(0.1, 0.2, 0.25).power(0.5), ion::ms(0.1, 0.2, 0.125));
+
+        let root = singleton::iswap().power(0.5);
+        assert_eq!(root.name(), "iswap^0.5");
+        assert_close(&(root.to_matrix() * root.to_matrix()), &singleton::iswap().to_matrix());
+        assert_eq!(singleton::ecr().power(3.0).name(), "ecr^3");
+    }
+
+    /// Controlled gates are named like Qiskit's, with controls on the lowest
+    /// qubits
+    #[test]
+    fn test_control() {
+        assert_eq!(singleton::x().control(1, None), singleton::cx());
+        assert_eq!(singleton::x().control(3, None), singleton::c3x());
+        assert_eq!(singleton::cx().control(1, None), singleton::ccx());
+        assert_eq!(singleton::sx().control(3, None), singleton::c3sx());
+        assert_eq!(
+            parameterized::u(0.1, 0.2, 0.3).control(1, None),
+            parameterized::cu(0.1, 0.2, 0.3, 0.0)
+        );
+
+        // the generic construction agrees with the library
+        for (gate, expected) in [
+            (singleton::hadamard(), singleton::ch()),
+            (singleton::swap(), singleton::cswap()),
+            (parameterized::ry(0.3), parameterized::cry(0.3)),
+        ] {
+            let generic = gate.builder().name("g".to_string()).build().control(1, None);
+            assert_eq!(generic.name(), "cg");
+            assert_close(&generic.to_matrix(), &expected.to_matrix());
+        }
+
+        let cch = singleton::hadamard().control(2, None);
+        assert_eq!(cch.name(), "c2h");
+        assert_eq!(cch.num_qubits(), 3);
+
+        // an open control acts when qubit 0 is |0>
+        let open = singleton::x().control(1, Some(0));
+        assert_eq!(open.name(), "cx_o0");
+        let mut expected = DMatrix::identity(4, 4);
+        expected.swap_columns(0, 2);
+        assert_close(&open.to_matrix(), &expected);
+    }
+
+    /// A user gate that reuses a library name keeps its own matrix
+    #[test]
+    fn test_control_name_clash() {
+        let fake_h = singleton::x().builder().name("h".to_string()).build();
+        let controlled = fake_h.control(1, None);
+        assert_eq!(controlled.name(), "ch");
+        assert_close(&controlled.to_matrix(), &singleton::cx().to_matrix());
+    }
+
+    /// User gates that reuse a library name are inverted and raised to powers
+    /// generically
+    #[test]
+    fn test_algebra_name_clash() {
+        let fake_s = singleton::t().builder().name("s".to_string()).build();
+        let inverse = fake_s.inverse();
+        assert_eq!(inverse.name(), "s_dg");
+        assert_close(&inverse.to_matrix(), &singleton::tdg().to_matrix());
+
+        let fake_x = singleton::z().builder().name("x".to_string()).build();
+        let root = fake_x.power(0.5);
+        assert_eq!(root.name(), "x^0.5");
+        assert_close(&root.to_matrix(), &singleton::s().to_matrix());
+
+        let fake_rx = parameterized::ry(0.3).builder().name("rx".to_string()).build();
+        let squared = fake_rx.power(2.0);
+        assert_eq!(squared.name(), "rx^2");
+        assert_close(&squared.to_matrix(), &parameterized::ry(0.6).to_matrix());
+    }
+
+    /// Matrices that cannot be unitarily diagonalized have no decomposition
+    #[test]
+    fn test_unitary_eigen_non_normal() {
+        let one = c64::new(1.0, 0.0);
+        let shear = DMatrix::from_row_slice(2, 2, &[one, one, c64::new(0.0, 0.0), one]);
+        assert!(unitary_eigen(&shear).is_none());
+        assert!(principal_generator(&shear).is_none());
+        assert!(unitary_eigen(&singleton::iswap().to_matrix()).is_some());
+    }
+
+    /// Gates without a library rule must be bound
+    #[test]
+    #[should_panic(expected = "unbound parameters")]
+    fn test_unbound_power() {
+        let gate = singleton::x().builder().name("custom".to_string()).params(vec![symbol("a")]).build();
+        gate.power(0.5);
+    }
+
+    /// π/2 about X twice is X up to phase
+    #[test]
+    fn test_rotation_power() {
+        let rx = parameterized::rx(FRAC_PI_2).power(2.0);
+        assert_close(&(rx.to_matrix() * c64::new(0.0, 1.0)), &singleton::x().to_matrix());
+    }
+}
@@ -0,0 +1,458 @@
+use nalgebra::base::DMatrix;
+
+use crate::c64;
+use crate::error::Mismatch;
+
+use super::algebra::principal_generator;
+use super::{Gate, Hamiltonian, HamiltonianComponent, TimeDependence, TimeUnit};
+
+/// Slices used to evolve a time-dependent Hamiltonian when verifying a gate.
+const VERIFY_STEPS: usize = 100;
+
+/// How [`Hamiltonian::propagator`] integrates the Schrödinger equation
+/// `dU/dt = -i H(t) U`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum PropagationMethod {
+    /// `exp(-i H (t1 - t0))` with `H` taken at the midpoint. Exact when the
+    /// Hamiltonian is constant.
+    TimeIndependent,
+    /// Product of `steps` exponentials, each with `H` constant at the
+    /// midpoint of its slice. Second order in the slice width.
+    PiecewiseConstant { steps: usize },
+    /// First Magnus term over `steps` slices, integrating `H` over each slice
+    /// at its two Gauss-Legendre nodes. Second order in the slice width like
+    /// `PiecewiseConstant`, but exact for commuting Hamiltonians whose time
+    /// dependence is a polynomial of degree at most three.
+    Magnus2 { steps: usize },
+    /// Fourth-order Magnus expansion over `steps` slices, sampling `H` at the
+    /// two Gauss-Legendre nodes of each slice.
+    Magnus4 { steps: usize },
+}
+
+/// Process fidelity `|tr(U†V)|² / d²` between two `d`-dimensional unitaries,
+/// 1 when they are equal up to a global phase.
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
+pub struct Fidelity(f64);
+
+impl Fidelity {
+    pub fn between(u: &DMatrix<c64>, v: &DMatrix<c64>) -> Self {
+        let dim = u.nrows() as f64;
+        Fidelity(u.dotc(v).norm_sqr() / (dim * dim))
+    }
+
+    pub fn value(&self) -> f64 {
+        self.0
+    }
+
+    /// `1 - fidelity`
+    pub fn infidelity(&self) -> f64 {
+        1.0 - self.0
+    }
+}
+
+impl Hamiltonian {
+    /// The constant Hamiltonian `H = i log(U) / T` generating `unitary` over
+    /// `duration` seconds, from the principal matrix logarithm. Its one
+    /// component has the dimensionless generator as operator and `1 / T` as
+    /// constant. Returns `None` if `unitary` cannot be diagonalized, e.g.
+    /// because it is not unitary.
+    pub fn from_unitary(unitary: &DMatrix<c64>, duration: f64) -> Option<Hamiltonian> {
+        Some(Hamiltonian::new(vec![HamiltonianComponent::new(
+            TimeDependence::unit(),
+            c64::new(1.0 / duration, 0.0),
+            principal_generator(unitary)?,
+        )]))
+    }
+
+    /// The total Hamiltonian at time `t`, in seconds.
+    ///
+    /// Panics if the Hamiltonian has no components, as its dimension is then
+    /// unknown.
+    pub fn at(&self, t: f64) -> DMatrix<c64> {
+        let (first, rest) = self
+            .components
+            .split_first()
+            .expect("Hamiltonian has no components");
+        rest.iter()
+            .fold(first.calculate(t), |total, component| total + component.calculate(t))
+    }
+
+    /// The unitary generated by the Hamiltonian from `t0` to `t1`, in
+    /// seconds.
+    ///
+    /// Panics if the Hamiltonian has no components or `method` has no steps.
+    pub fn propagator(&self, t0: f64, t1: f64, method: PropagationMethod) -> DMatrix<c64> {
+        let steps = match method {
+            PropagationMethod::TimeIndependent => 1,
+            PropagationMethod::PiecewiseConstant { steps }
+            | PropagationMethod::Magnus2 { steps }
+            | PropagationMethod::Magnus4 { steps } => steps,
+        };
+        assert!(steps > 0, "propagation needs at least one step");
+
+        let dim = self.at(t0).nrows();
+        let h = (t1 - t0) / steps as f64;
+        let minus_i = c64::new(0.0, -1.0);
+        // Gauss-Legendre nodes at 1/2 ∓ √3/6 of each slice
+        let offset = 3f64.sqrt() / 6.0;
+        (0..steps).fold(DMatrix::identity(dim, dim), |propagator, step| {
+            let start = t0 + step as f64 * h;
+            let exponent = match method {
+                PropagationMethod::TimeIndependent | PropagationMethod::PiecewiseConstant { .. } => {
+                    self.at(start + h / 2.0) * (minus_i * h)
+                }
+                PropagationMethod::Magnus2 { .. } => {
+                    (self.at(start + (0.5 - offset) * h) + self.at(start + (0.5 + offset) * h))
+                        * (minus_i * h / 2.0)
+                }
+                PropagationMethod::Magnus4 { .. } => {
+                    let a1 = self.at(start + (0.5 - offset) * h) * minus_i;
+                    let a2 = self.at(start + (0.5 + offset) * h) * minus_i;
+                    let commutator = &a2 * &a1 - &a1 * &a2;
+                    (a1 + a2) * c64::new(h / 2.0, 0.0)
+                        + commutator * c64::new(3f64.sqrt() * h * h / 12.0, 0.0)
+                }
+            };
+            // later slices act after earlier ones
+            exponent.exp() * propagator
+        })
+    }
+}
+
+impl Gate {
+    /// The gate taking `duration` seconds, with the generator of its matrix
+    /// as its Hamiltonian, see [`Hamiltonian::from_unitary`]. The generator
+    /// is recomputed whenever the matrix is, e.g. on binding parameters.
+    pub fn with_generator(&self, duration: f64) -> Gate {
+        self.builder()
+            .duration(duration)
+            .unit(TimeUnit::S)
+            .generator()
+            .build()
+    }
+
+    /// Recompute the Hamiltonian of a gate using the generator of its matrix.
+    pub(super) fn refresh_generator(&mut self) {
+        if !self.generator {
+            return;
+        }
+        self.hamiltonian = self
+            .to_seconds(None)
+            .and_then(|duration| Hamiltonian::from_unitary(&self.matrix, duration));
+    }
+
+    /// Check that evolving the gate's Hamiltonian over its duration gives its
+    /// matrix up to a global phase, within an infidelity of `tolerance`.
+    ///
+    /// Constant Hamiltonians are exponentiated directly, time-dependent ones
+    /// with a fourth-order Magnus expansion. The duration must be in physical
+    /// units.
+    pub fn verify_hamiltonian(&self, tolerance: f64) -> Result<Fidelity, Mismatch> {
+        let name = self.name.clone();
+        let Some(hamiltonian) = &self.hamiltonian else {
+            return Err(Mismatch::MissingHamiltonian { name });
+        };
+        if !self.is_bound() {
+            return Err(Mismatch::UnboundParameters { name });
+        }
+        let Some(duration) = self.to_seconds(None) else {
+            return Err(Mismatch::UnknownDuration { name });
+        };
+        // every component must match, as mixed sizes cannot be summed
+        let matrix = self.matrix.nrows();
+        let mismatch = if hamiltonian.components.is_empty() {
+            Some(0)
+        } else {
+            hamiltonian.components.iter().find_map(|component| {
+                let (rows, cols) = component.operator.shape();
+                if rows != matrix {
+                    Some(rows)
+                } else if cols != matrix {
+                    Some(cols)
+                } else {
+                    None
+                }
+            })
+        };
+        if let Some(dimension) = mismatch {
+            return Err(Mismatch::DimensionMismatch {
+                name,
+                matrix,
+                hamiltonian: dimension,
+            });
+        }
+
+        let constant = hamiltonian
+            .components
+            .iter()
+            .all(|component| component.time_dependence().is_constant());
+        let method = if constant {
+            PropagationMethod::TimeIndependent
+        } else {
+            PropagationMethod::Magnus4 {
+                steps: VERIFY_STEPS,
+            }
+        };
+        let propagator = hamiltonian.propagator(0.0, duration, method);
+        let fidelity = Fidelity::between(&propagator, &self.matrix);
+        if fidelity.infidelity() > tolerance {
+            return Err(Mismatch::Fidelity {
+                name,
+                fidelity,
+                tolerance,
+            });
+        }
+        Ok(fidelity)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+    use crate::approx::ApproxEq;
+    use std::collections::HashMap;
+
+    use crate::gates::{ion, parameterized, singleton, GateRegistry};
+    use crate::parameter::{Param, Parameter, ParameterExpression};
+    use crate::operations::{HamiltonianComponent, TimeDependence, TimeUnit};
+
+    fn pauli_x() -> DMatrix<c64> {
+        DMatrix::from_row_slice(2, 2, &[0.0, 1.0, 1.0, 0.0].map(|x| c64::new(x, 0.0)))
+    }
+
+    fn pauli_z() -> DMatrix<c64> {
+        DMatrix::from_row_slice(2, 2, &[1.0, 0.0, 0.0, -1.0].map(|x| c64::new(x, 0.0)))
+    }
+
+    /// Gaussian X drive with a constant Z detuning, which do not commute
+    fn driven(sigma: f64, detuning: f64) -> Hamiltonian {
+        let drive = TimeDependence::Gaussian {
+            amp: 1.0,
+            center: 0.0,
+            sigma,
+        };
+        Hamiltonian::new(vec![
+            HamiltonianComponent::new(drive, c64::new(PI / (2.0 * sigma * (2.0 * PI).sqrt()), 0.0), pauli_x()),
+            HamiltonianComponent::new(TimeDependence::unit(), c64::new(detuning, 0.0), pauli_z()),
+        ])
+    }
+
+    fn distance(a: &DMatrix<c64>, b: &DMatrix<c64>) -> f64 {
+        (a - b).norm()
+    }
+
+    /// A gate's Hamiltonian over its duration reproduces its matrix
+    #[test]
+    fn test_gate_hamiltonian() {
+        let duration = 10e-6;
+        let gate = ion::with_hamiltonian(&ion::gpi2(0.1), duration).unwrap();
+        let hamiltonian = gate.hamiltonian().unwrap();
+        for method in [
+            PropagationMethod::TimeIndependent,
+            PropagationMethod::PiecewiseConstant { steps: 4 },
+            PropagationMethod::Magnus2 { steps: 4 },
+            PropagationMethod::Magnus4 { steps: 2 },
+        ] {
+            let propagator = hamiltonian.propagator(0.0, duration, method);
+            assert!(propagator.approx_eq_up_to_phase(gate.matrix()), "{:?}", method);
+        }
+    }
+
+    /// A π-area Gaussian on resonance flips the qubit
+    #[test]
+    fn test_resonant_pulse() {
+        let sigma = 10e-9;
+        let propagator = driven(sigma, 0.0).propagator(
+            -5.0 * sigma,
+            5.0 * sigma,
+            PropagationMethod::Magnus4 { steps: 20 },
+        );
+        // exp(-iπX/2) = -iX
+        let expected = pauli_x() * c64::new(0.0, -1.0);
+        assert!(distance(&propagator, &expected) < 1e-5);
+    }
+
+    /// Gauss-Legendre nodes integrate a cubic drive exactly, the midpoint
+    /// does not
+    #[test]
+    fn test_magnus2_quadrature() {
+        // ∫₀¹ t³ dt = 1/4, so the Z rotation has angle π/2
+        let cubic = Hamiltonian::new(vec![HamiltonianComponent::new(
+            TimeDependence::custom(|t| c64::new(t * t * t, 0.0)),
+            c64::new(PI, 0.0),
+            pauli_z(),
+        )]);
+        let expected = (pauli_z() * c64::new(0.0, -PI / 4.0)).exp();
+
+        let magnus = cubic.propagator(0.0, 1.0, PropagationMethod::Magnus2 { steps: 1 });
+        assert!(distance(&magnus, &expected) < 1e-12);
+        let midpoint = cubic.propagator(0.0, 1.0, PropagationMethod::PiecewiseConstant { steps: 1 });
+        assert!(distance(&midpoint, &expected) > 0.1);
+    }
+
+    /// Higher orders converge faster to the time-ordered exponential
+    #[test]
+    fn test_convergence() {
+        let sigma = 10e-9;
+        let hamiltonian = driven(sigma, 2.0 * PI * 20e6);
+        let (t0, t1) = (-4.0 * sigma, 4.0 * sigma);
+        let reference = hamiltonian.propagator(t0, t1, PropagationMethod::Magnus4 { steps: 400 });
+
+        let error = |method| distance(&hamiltonian.propagator(t0, t1, method), &reference);
+        let sliced = error(PropagationMethod::PiecewiseConstant { steps: 20 });
+        let second = error(PropagationMethod::Magnus2 { steps: 20 });
+        let fourth = error(PropagationMethod::Magnus4 { steps: 20 });
+        assert!(fourth < second / 10.0, "{} vs {}", fourth, second);
+        // doubling the steps divides the error by 2^order
+        let halved = error(PropagationMethod::PiecewiseConstant { steps: 40 });
+        assert!((sliced / halved - 4.0).abs() < 0.2);
+        let halved = error(PropagationMethod::Magnus2 { steps: 40 });
+        assert!((second / halved - 4.0).abs() < 0.2);
+        let halved = error(PropagationMethod::Magnus4 { steps: 40 });
+        assert!((fourth / halved - 16.0).abs() < 1.0);
+
+        let unitary = hamiltonian.propagator(t0, t1, PropagationMethod::Magnus4 { steps: 20 });
+        assert!(distance(&(unitary.adjoint() * &unitary), &DMatrix::identity(2, 2)) < 1e-12);
+    }
+
+    /// Library Hamiltonians generate their gates
+    #[test]
+    fn test_verify_library() {
+        for gate in [
+            ion::gpi(0.2),
+            ion::gpi2(0.3),
+            ion::ms(0.1, 0.4, 0.2),
+            ion::zz(0.15),
+            ion::gms(3, 0.25),
+        ] {
+            let gate = ion::with_hamiltonian(&gate, 20e-6).unwrap();
+            let fidelity = gate.verify_hamiltonian(1e-9).unwrap();
+            assert!(fidelity.infidelity() < 1e-9, "{}", gate.name());
+        }
+    }
+
+    /// Hand-edited Hamiltonians that disagree with the matrix are reported
+    #[test]
+    fn test_verify_mismatch() {
+        let gpi2 = ion::with_hamiltonian(&ion::gpi2(0.3), 10e-6).unwrap();
+        let wrong = gpi2.builder().matrix(ion::gpi2(0.35).to_matrix()).build();
+        let Err(Mismatch::Fidelity { fidelity, .. }) = wrong.verify_hamiltonian(1e-6) else {
+            panic!("expected a fidelity mismatch");
+        };
+        assert!(fidelity.value() < 1.0 - 1e-6);
+        assert!(wrong.verify_hamiltonian(0.1).is_ok());
+
+        let bare = Gate::new(
+            "bare".to_string(),
+            vec![],
+            Some(1.0),
+            TimeUnit::US,
+            singleton::x().to_matrix(),
+            None,
+        );
+        assert_eq!(
+            bare.verify_hamiltonian(1e-6),
+            Err(Mismatch::MissingHamiltonian { name: "bare".to_string() })
+        );
+        let in_samples = gpi2.builder().unit(TimeUnit::DT).build();
+        assert!(matches!(
+            in_samples.verify_hamiltonian(1e-6),
+            Err(Mismatch::UnknownDuration { .. })
+        ));
+        let two_qubits = gpi2.builder().matrix(singleton::cx().to_matrix()).build();
+        assert!(matches!(
+            two_qubits.verify_hamiltonian(1e-6),
+            Err(Mismatch::DimensionMismatch { matrix: 4, hamiltonian: 2, .. })
+        ));
+
+        // a later component of another size is caught before evolving
+        let mut components = gpi2.hamiltonian().unwrap().components().clone();
+        components.push(HamiltonianComponent::new(
+            TimeDependence::unit(),
+            c64::new(1.0, 0.0),
+            DMatrix::identity(4, 4),
+        ));
+        let mixed = gpi2.builder().hamiltonian(Hamiltonian::new(components)).build();
+        assert!(matches!(
+            mixed.verify_hamiltonian(1e-6),
+            Err(Mismatch::DimensionMismatch { matrix: 2, hamiltonian: 4, .. })
+        ));
+    }
+
+    /// Time-dependent Hamiltonians are evolved by Magnus expansion
+    #[test]
+    fn test_verify_pulse() {
+        let sigma = 10e-9;
+        let pulse = Hamiltonian::new(vec![HamiltonianComponent::new(
+            TimeDependence::Gaussian {
+                amp: 1.0,
+                center: 5.0 * sigma,
+                sigma,
+            },
+            c64::new(PI / (2.0 * sigma * (2.0 * PI).sqrt()), 0.0),
+            pauli_x(),
+        )]);
+        let builder = singleton::x()
+            .builder()
+            .duration(100.0)
+            .unit(TimeUnit::NS)
+            .hamiltonian(pulse)
+            .verify_hamiltonian(1e-9);
+        assert!(builder.clone().try_build().is_ok());
+        let wrong = builder.matrix(singleton::y().to_matrix()).try_build();
+        assert!(matches!(wrong, Err(Mismatch::Fidelity { .. })));
+    }
+
+    /// Standard gates only get a Hamiltonian on request, which then generates
+    /// them over the requested duration
+    #[test]
+    fn test_standard_generators() {
+        let registry = GateRegistry::standard().with_generators(50e-9);
+        for name in registry.names() {
+            let definition = registry.get(name, 0, 0).unwrap();
+            let num_params = definition.num_params().unwrap_or_default();
+            let params: Vec<f64> = (1..=num_params).map(|k| 0.1 * k as f64).collect();
+            let gate = definition.build(&params);
+            assert_eq!(gate.to_seconds(None), Some(50e-9), "{}", name);
+            assert!(gate.verify_hamiltonian(1e-9).is_ok(), "{}", name);
+        }
+        for gate in [singleton::x(), parameterized::rx(0.3)] {
+            assert_eq!((gate.duration(), gate.hamiltonian()), (None, None));
+        }
+    }
+
+    /// Generators are Hermitian, principal and follow the matrix
+    #[test]
+    fn test_with_generator() {
+        let swap = singleton::swap().with_generator(2e-6);
+        assert_eq!((swap.duration(), swap.unit()), (Some(2e-6), &TimeUnit::S));
+        let component = &swap.hamiltonian().unwrap().components()[0];
+        assert_eq!(*component.constant(), c64::new(0.5e6, 0.0));
+        let generator = component.operator();
+        assert!(distance(generator, &generator.adjoint()) < 1e-12);
+        // SWAP = exp(-iπ(I - SWAP)/2), with eigenvalues 0 and -π
+        let identity = DMatrix::identity(4, 4);
+        let expected = (&identity - singleton::swap().to_matrix()) * c64::new(-PI / 2.0, 0.0);
+        assert!(distance(generator, &expected) < 1e-9);
+        assert!(swap.verify_hamiltonian(1e-12).is_ok());
+
+        // rebinding recomputes the generator with the matrix
+        let theta = Parameter::new("theta".to_string());
+        let symbol = Param::Expression(ParameterExpression::Symbol(theta.clone()));
+        let rx = parameterized::rx(0.0).with_generator(1e-6).with_params(vec![symbol]);
+        let bound = rx.bind(&HashMap::from([(theta, 0.8)]));
+        assert_eq!(bound.hamiltonian(), parameterized::rx(0.8).with_generator(1e-6).hamiltonian());
+        assert!(bound.verify_hamiltonian(1e-12).is_ok());
+
+        // controlled library gates keep generating their matrix
+        let cx = singleton::x().with_generator(1e-6).control(1, None);
+        assert_eq!(cx.name(), "cx");
+        assert!(cx.verify_hamiltonian(1e-12).is_ok());
+
+        // hand-set Hamiltonians are kept
+        let zz = ion::with_hamiltonian(&ion::zz(0.1), 100e-6).unwrap();
+        assert_eq!(zz.builder().name("zz2".to_string()).build().hamiltonian(), zz.hamiltonian());
+    }
+}
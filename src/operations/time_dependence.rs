@@ -0,0 +1,309 @@
+use std::f64::consts::PI;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::c64;
+
+#[cfg(feature = "serde")]
+use serde::{Deserialize, Serialize};
+
+/// Arbitrary time dependence of a Hamiltonian term, with `t` in seconds.
+pub type TimeDependentFn = Arc<dyn Fn(f64) -> c64 + Send + Sync>;
+
+/// Time dependence of a [`HamiltonianComponent`](super::HamiltonianComponent),
+/// the envelope multiplying its constant and operator.
+///
+/// Times and widths are in seconds. The built-in shapes are plain data, so
+/// they can be compared, cloned and, with the `serde` feature, stored in
+/// configuration files; [`TimeDependence::Custom`] runs arbitrary code instead
+/// and is skipped by serialization.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum TimeDependence {
+    /// `amp` at all times
+    Constant { amp: f64 },
+    /// `amp` from `start` for `duration`, zero elsewhere
+    Square { amp: f64, start: f64, duration: f64 },
+    /// Gaussian of height `amp` centred on `center`
+    Gaussian { amp: f64, center: f64, sigma: f64 },
+    /// Flat top of `width` in the middle of `[start, start + duration]`,
+    /// reached by Gaussian flanks of width `sigma`, zero outside the interval
+    GaussianSquare {
+        amp: f64,
+        start: f64,
+        duration: f64,
+        sigma: f64,
+        width: f64,
+    },
+    /// Flat top over `[start, start + duration]`, rising and falling as a
+    /// half cosine over `ramp` at either end
+    CosineRamp {
+        amp: f64,
+        start: f64,
+        duration: f64,
+        ramp: f64,
+    },
+    /// Gaussian with a derivative quadrature, `g(t) + iβ g'(t)`, suppressing
+    /// leakage to a third level
+    Drag {
+        amp: f64,
+        center: f64,
+        sigma: f64,
+        beta: f64,
+    },
+    /// `samples[k]` over `[start + k dt, start + (k + 1) dt)`, zero outside
+    /// the samples, as an arbitrary waveform from a backend
+    PiecewiseConstant {
+        start: f64,
+        dt: f64,
+        samples: Vec<c64>,
+    },
+    /// Sum of the shapes
+    Sum(Vec<TimeDependence>),
+    /// Product of the shapes
+    Product(Vec<TimeDependence>),
+    /// Any other time dependence
+    #[cfg_attr(feature = "serde", serde(skip))]
+    Custom(CustomTimeFn),
+}
+
+/// A [`TimeDependentFn`]. Two are equal only if they are the same function.
+#[derive(Clone)]
+pub struct CustomTimeFn(TimeDependentFn);
+
+impl TimeDependence {
+    /// Constant one, for terms whose strength is all in their constant.
+    pub fn unit() -> Self {
+        TimeDependence::Constant { amp: 1.0 }
+    }
+
+    /// Time dependence given by `f`, which may capture its parameters.
+    pub fn custom<F>(f: F) -> Self
+    where
+        F: Fn(f64) -> c64 + Send + Sync + 'static,
+    {
+        TimeDependence::Custom(CustomTimeFn(Arc::new(f)))
+    }
+
+    /// Value at time `t`, in seconds.
+    pub fn at(&self, t: f64) -> c64 {
+        let real = |value: f64| c64::new(value, 0.0);
+        match self {
+            TimeDependence::Constant { amp } => real(*amp),
+            TimeDependence::Square {
+                amp,
+                start,
+                duration,
+            } => real(if (*start..start + duration).contains(&t) { *amp } else { 0.0 }),
+            TimeDependence::Gaussian { amp, center, sigma } => real(amp * gaussian(t - center, *sigma)),
+            TimeDependence::GaussianSquare {
+                amp,
+                start,
+                duration,
+                sigma,
+                width,
+            } => {
+                let offset = t - start;
+                let flank = (duration - width) / 2.0;
+                let envelope = if !(0.0..=*duration).contains(&offset) {
+                    0.0
+                } else if offset < flank {
+                    gaussian(offset - flank, *sigma)
+                } else if offset > flank + width {
+                    gaussian(offset - flank - width, *sigma)
+                } else {
+                    1.0
+                };
+                real(amp * envelope)
+            }
+            TimeDependence::CosineRamp {
+                amp,
+                start,
+                duration,
+                ramp,
+            } => {
+                let offset = t - start;
+                // distance into the pulse from its nearer end
+                let edge = offset.min(duration - offset);
+                let envelope = if edge < 0.0 {
+                    0.0
+                } else if edge < *ramp {
+                    (1.0 - (PI * edge / ramp).cos()) / 2.0
+                } else {
+                    1.0
+                };
+                real(amp * envelope)
+            }
+            TimeDependence::Drag {
+                amp,
+                center,
+                sigma,
+                beta,
+            } => {
+                let offset = t - center;
+                let g = amp * gaussian(offset, *sigma);
+                c64::new(g, -beta * offset / (sigma * sigma) * g)
+            }
+            TimeDependence::PiecewiseConstant { start, dt, samples } => {
+                let k = ((t - start) / dt).floor();
+                if k < 0.0 || k >= samples.len() as f64 {
+                    c64::new(0.0, 0.0)
+                } else {
+                    samples[k as usize]
+                }
+            }
+            TimeDependence::Sum(shapes) => shapes.iter().map(|shape| shape.at(t)).sum(),
+            TimeDependence::Product(shapes) => shapes.iter().map(|shape| shape.at(t)).product(),
+            TimeDependence::Custom(CustomTimeFn(f)) => f(t),
+        }
+    }
+
+    /// Whether the value is the same at all times.
+    pub fn is_constant(&self) -> bool {
+        match self {
+            TimeDependence::Constant { .. } => true,
+            TimeDependence::Sum(shapes) | TimeDependence::Product(shapes) => {
+                shapes.iter().all(TimeDependence::is_constant)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Unnormalized Gaussian, 1 at `x = 0`.
+fn gaussian(x: f64, sigma: f64) -> f64 {
+    (-x * x / (2.0 * sigma * sigma)).exp()
+}
+
+impl fmt::Debug for CustomTimeFn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("CustomTimeFn(..)")
+    }
+}
+
+impl PartialEq for CustomTimeFn {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(actual: c64, expected: c64) {
+        assert!((actual - expected).norm() < 1e-12, "{} != {}", actual, expected);
+    }
+
+    /// Pulse shapes have their nominal values at landmark times
+    #[test]
+    fn test_shapes() {
+        let square = TimeDependence::Square {
+            amp: 2.0,
+            start: 1e-6,
+            duration: 1e-6,
+        };
+        assert_eq!(square.at(0.5e-6).re, 0.0);
+        assert_eq!(square.at(1.5e-6).re, 2.0);
+        assert_eq!(square.at(2e-6).re, 0.0);
+
+        let sigma = 10e-9;
+        let gaussian = TimeDependence::Gaussian {
+            amp: 1.0,
+            center: 50e-9,
+            sigma,
+        };
+        assert_close(gaussian.at(50e-9), c64::new(1.0, 0.0));
+        assert_close(gaussian.at(60e-9), c64::new((-0.5f64).exp(), 0.0));
+
+        let flat_top = TimeDependence::GaussianSquare {
+            amp: 0.5,
+            start: 0.0,
+            duration: 100e-9,
+            sigma,
+            width: 60e-9,
+        };
+        assert_close(flat_top.at(50e-9), c64::new(0.5, 0.0));
+        assert_close(flat_top.at(20e-9), c64::new(0.5, 0.0));
+        assert_close(flat_top.at(10e-9), c64::new(0.5 * (-0.5f64).exp(), 0.0));
+        assert_close(flat_top.at(90e-9), c64::new(0.5 * (-0.5f64).exp(), 0.0));
+        assert_eq!(flat_top.at(101e-9).re, 0.0);
+
+        let ramp = TimeDependence::CosineRamp {
+            amp: 1.0,
+            start: 0.0,
+            duration: 100e-9,
+            ramp: 20e-9,
+        };
+        assert_close(ramp.at(0.0), c64::new(0.0, 0.0));
+        assert_close(ramp.at(10e-9), c64::new(0.5, 0.0));
+        assert_close(ramp.at(50e-9), c64::new(1.0, 0.0));
+        assert_close(ramp.at(90e-9), c64::new(0.5, 0.0));
+        assert_eq!(ramp.at(-1e-9).re, 0.0);
+    }
+
+    /// The DRAG quadrature is β times the derivative of the Gaussian
+    #[test]
+    fn test_drag() {
+        let (center, sigma, beta) = (50e-9, 10e-9, 2e-9);
+        let drag = TimeDependence::Drag {
+            amp: 1.0,
+            center,
+            sigma,
+            beta,
+        };
+        let gaussian = TimeDependence::Gaussian {
+            amp: 1.0,
+            center,
+            sigma,
+        };
+        let (t, h) = (42e-9, 1e-13);
+        let derivative = (gaussian.at(t + h) - gaussian.at(t - h)).re / (2.0 * h);
+        assert!((drag.at(t).re - gaussian.at(t).re).abs() < 1e-12);
+        assert!((drag.at(t).im - beta * derivative).abs() < 1e-6);
+        assert_eq!(drag.at(center).im, 0.0);
+    }
+
+    /// Samples are held for `dt` each
+    #[test]
+    fn test_piecewise_constant() {
+        let samples = vec![c64::new(0.1, 0.0), c64::new(0.0, 0.2), c64::new(0.3, 0.0)];
+        let waveform = TimeDependence::PiecewiseConstant {
+            start: 1e-9,
+            dt: 2e-9,
+            samples: samples.clone(),
+        };
+        assert_eq!(waveform.at(0.5e-9), c64::new(0.0, 0.0));
+        assert_eq!(waveform.at(1.5e-9), samples[0]);
+        assert_eq!(waveform.at(3.5e-9), samples[1]);
+        assert_eq!(waveform.at(6.9e-9), samples[2]);
+        assert_eq!(waveform.at(7.1e-9), c64::new(0.0, 0.0));
+    }
+
+    /// Shapes combine, and closures capture their parameters
+    #[test]
+    fn test_combinations() {
+        let detuning = 2.0 * PI * 1e6;
+        let carrier = TimeDependence::custom(move |t| c64::from_polar(1.0, detuning * t));
+        let square = TimeDependence::Square {
+            amp: 3.0,
+            start: 0.0,
+            duration: 1e-6,
+        };
+        let offset = TimeDependence::Constant { amp: 1.0 };
+
+        let t = 0.25e-6;
+        let product = TimeDependence::Product(vec![square.clone(), carrier.clone()]);
+        assert_close(product.at(t), c64::new(0.0, 3.0));
+        let sum = TimeDependence::Sum(vec![square.clone(), offset.clone()]);
+        assert_close(sum.at(t), c64::new(4.0, 0.0));
+
+        assert!(TimeDependence::Sum(vec![offset.clone(), TimeDependence::unit()]).is_constant());
+        assert!(!sum.is_constant());
+
+        // custom functions are only equal to their clones
+        assert_eq!(product.clone(), product);
+        assert_ne!(carrier, TimeDependence::custom(move |t| c64::from_polar(1.0, detuning * t)));
+    }
+}
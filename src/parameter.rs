@@ -0,0 +1,316 @@
+use std::collections::HashMap;
+use std::fmt;
+
+/// A named symbolic parameter, e.g. `Parameter(theta)` or the vector element
+/// `ParameterVectorElement(theta[0])`, which is named `theta[0]`.
+///
+/// Parameters order by name like Qiskit's `QuantumCircuit.parameters`, with
+/// vector elements ordered by their numeric index (`theta[2]` before
+/// `theta[10]`).
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct Parameter {
+    name: String,
+}
+
+/// Functions that may appear in a parameter expression.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Function {
+    Sin,
+    Cos,
+    Tan,
+    Exp,
+    Log,
+    Sqrt,
+}
+
+/// A symbolic expression over parameters, as produced by Qiskit's
+/// `ParameterExpression`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum ParameterExpression {
+    Constant(f64),
+    Symbol(Parameter),
+    Neg(Box<ParameterExpression>),
+    Add(Box<ParameterExpression>, Box<ParameterExpression>),
+    Sub(Box<ParameterExpression>, Box<ParameterExpression>),
+    Mul(Box<ParameterExpression>, Box<ParameterExpression>),
+    Div(Box<ParameterExpression>, Box<ParameterExpression>),
+    Pow(Box<ParameterExpression>, Box<ParameterExpression>),
+    Call(Function, Box<ParameterExpression>),
+}
+
+/// A gate parameter: either a plain number or a symbolic expression.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Param {
+    Value(f64),
+    Expression(ParameterExpression),
+}
+
+impl Parameter {
+    pub fn new(name: String) -> Self {
+        Parameter { name }
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    /// Split a vector element name such as `theta[3]` into `("theta", 3)`.
+    fn sort_key(&self) -> (&str, Option<usize>) {
+        if let Some((base, rest)) = self.name.split_once('[') {
+            if let Some(Ok(index)) = rest.strip_suffix(']').map(str::parse) {
+                return (base, Some(index));
+            }
+        }
+        (&self.name, None)
+    }
+}
+
+impl Ord for Parameter {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.sort_key()
+            .cmp(&other.sort_key())
+            .then_with(|| self.name.cmp(&other.name))
+    }
+}
+
+impl PartialOrd for Parameter {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Function {
+    /// Look up a function by the name used in Qiskit's expression repr.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sin" => Some(Function::Sin),
+            "cos" => Some(Function::Cos),
+            "tan" => Some(Function::Tan),
+            "exp" => Some(Function::Exp),
+            "log" => Some(Function::Log),
+            "sqrt" => Some(Function::Sqrt),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Function::Sin => "sin",
+            Function::Cos => "cos",
+            Function::Tan => "tan",
+            Function::Exp => "exp",
+            Function::Log => "log",
+            Function::Sqrt => "sqrt",
+        }
+    }
+
+    pub fn apply(&self, x: f64) -> f64 {
+        match self {
+            Function::Sin => x.sin(),
+            Function::Cos => x.cos(),
+            Function::Tan => x.tan(),
+            Function::Exp => x.exp(),
+            Function::Log => x.ln(),
+            Function::Sqrt => x.sqrt(),
+        }
+    }
+}
+
+impl ParameterExpression {
+    /// The parameters appearing in the expression, in order of first
+    /// appearance.
+    pub fn parameters(&self) -> Vec<Parameter> {
+        let mut parameters = Vec::new();
+        self.collect_parameters(&mut parameters);
+        parameters
+    }
+
+    fn collect_parameters(&self, parameters: &mut Vec<Parameter>) {
+        match self {
+            ParameterExpression::Constant(_) => {}
+            ParameterExpression::Symbol(parameter) => {
+                if !parameters.contains(parameter) {
+                    parameters.push(parameter.clone());
+                }
+            }
+            ParameterExpression::Neg(inner) | ParameterExpression::Call(_, inner) => {
+                inner.collect_parameters(parameters)
+            }
+            ParameterExpression::Add(lhs, rhs)
+            | ParameterExpression::Sub(lhs, rhs)
+            | ParameterExpression::Mul(lhs, rhs)
+            | ParameterExpression::Div(lhs, rhs)
+            | ParameterExpression::Pow(lhs, rhs) => {
+                lhs.collect_parameters(parameters);
+                rhs.collect_parameters(parameters);
+            }
+        }
+    }
+
+    /// Substitute the given values for their parameters, folding any
+    /// subexpression that becomes constant. Parameters without a value are
+    /// left symbolic.
+    pub fn bind(&self, values: &HashMap<Parameter, f64>) -> ParameterExpression {
+        use ParameterExpression::*;
+
+        let binary = |lhs: &ParameterExpression,
+                      rhs: &ParameterExpression,
+                      op: fn(f64, f64) -> f64,
+                      build: fn(Box<ParameterExpression>, Box<ParameterExpression>) -> ParameterExpression| {
+            match (lhs.bind(values), rhs.bind(values)) {
+                (Constant(a), Constant(b)) => Constant(op(a, b)),
+                (a, b) => build(Box::new(a), Box::new(b)),
+            }
+        };
+
+        match self {
+            Constant(value) => Constant(*value),
+            Symbol(parameter) => match values.get(parameter) {
+                Some(value) => Constant(*value),
+                None => Symbol(parameter.clone()),
+            },
+            Neg(inner) => match inner.bind(values) {
+                Constant(value) => Constant(-value),
+                inner => Neg(Box::new(inner)),
+            },
+            Call(function, inner) => match inner.bind(values) {
+                Constant(value) => Constant(function.apply(value)),
+                inner => Call(*function, Box::new(inner)),
+            },
+            Add(lhs, rhs) => binary(lhs, rhs, |a, b| a + b, Add),
+            Sub(lhs, rhs) => binary(lhs, rhs, |a, b| a - b, Sub),
+            Mul(lhs, rhs) => binary(lhs, rhs, |a, b| a * b, Mul),
+            Div(lhs, rhs) => binary(lhs, rhs, |a, b| a / b, Div),
+            Pow(lhs, rhs) => binary(lhs, rhs, f64::powf, Pow),
+        }
+    }
+
+    /// The numeric value of the expression, if it has no free parameters.
+    pub fn value(&self) -> Option<f64> {
+        match self.bind(&HashMap::new()) {
+            ParameterExpression::Constant(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl Param {
+    /// The numeric value of the parameter, if it is fully bound.
+    pub fn value(&self) -> Option<f64> {
+        match self {
+            Param::Value(value) => Some(*value),
+            Param::Expression(expr) => expr.value(),
+        }
+    }
+
+    /// The free parameters, in order of first appearance.
+    pub fn parameters(&self) -> Vec<Parameter> {
+        match self {
+            Param::Value(_) => vec![],
+            Param::Expression(expr) => expr.parameters(),
+        }
+    }
+
+    /// Substitute values for parameters, collapsing to a [`Param::Value`] when
+    /// nothing symbolic remains.
+    pub fn bind(&self, values: &HashMap<Parameter, f64>) -> Param {
+        match self {
+            Param::Value(value) => Param::Value(*value),
+            Param::Expression(expr) => match expr.bind(values) {
+                ParameterExpression::Constant(value) => Param::Value(value),
+                expr => Param::Expression(expr),
+            },
+        }
+    }
+}
+
+impl std::ops::Neg for Param {
+    type Output = Param;
+
+    fn neg(self) -> Param {
+        match self {
+            Param::Value(value) => Param::Value(-value),
+            Param::Expression(expr) => Param::Expression(ParameterExpression::Neg(Box::new(expr))),
+        }
+    }
+}
+
+impl std::ops::Add<f64> for Param {
+    type Output = Param;
+
+    fn add(self, rhs: f64) -> Param {
+        match self {
+            Param::Value(value) => Param::Value(value + rhs),
+            Param::Expression(expr) => Param::Expression(ParameterExpression::Add(
+                Box::new(expr),
+                Box::new(ParameterExpression::Constant(rhs)),
+            )),
+        }
+    }
+}
+
+impl std::ops::Mul<f64> for Param {
+    type Output = Param;
+
+    fn mul(self, rhs: f64) -> Param {
+        match self {
+            Param::Value(value) => Param::Value(value * rhs),
+            Param::Expression(expr) => Param::Expression(ParameterExpression::Mul(
+                Box::new(ParameterExpression::Constant(rhs)),
+                Box::new(expr),
+            )),
+        }
+    }
+}
+
+impl From<f64> for Param {
+    fn from(value: f64) -> Self {
+        Param::Value(value)
+    }
+}
+
+impl From<Parameter> for ParameterExpression {
+    fn from(parameter: Parameter) -> Self {
+        ParameterExpression::Symbol(parameter)
+    }
+}
+
+impl From<ParameterExpression> for Param {
+    fn from(expr: ParameterExpression) -> Self {
+        Param::Expression(expr)
+    }
+}
+
+impl fmt::Display for Parameter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+impl fmt::Display for ParameterExpression {
+    /// Fully parenthesized form, e.g. `((2 * theta) + 0.5)`.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParameterExpression::Constant(value) => write!(f, "{}", value),
+            ParameterExpression::Symbol(parameter) => write!(f, "{}", parameter),
+            ParameterExpression::Neg(inner) => write!(f, "(-{})", inner),
+            ParameterExpression::Add(lhs, rhs) => write!(f, "({} + {})", lhs, rhs),
+            ParameterExpression::Sub(lhs, rhs) => write!(f, "({} - {})", lhs, rhs),
+            ParameterExpression::Mul(lhs, rhs) => write!(f, "({} * {})", lhs, rhs),
+            ParameterExpression::Div(lhs, rhs) => write!(f, "({} / {})", lhs, rhs),
+            ParameterExpression::Pow(lhs, rhs) => write!(f, "({} ** {})", lhs, rhs),
+            ParameterExpression::Call(function, inner) => {
+                write!(f, "{}({})", function.name(), inner)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Param {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Param::Value(value) => write!(f, "{}", value),
+            Param::Expression(expr) => write!(f, "{}", expr),
+        }
+    }
+}
@@ -1,38 +1,104 @@
 mod parser;
 mod tokenizer;
 
+pub use tokenizer::{Span, SpannedToken, Token};
+
+use std::collections::HashMap;
+
 use crate::{
-    bit::{Clbit, Qubit},
+    bit::{AncillaQubit, BitOps, Clbit, Qubit},
     circuit_instruction::CircuitInstruction,
+    error::{ParameterError, ParseError},
+    gates::GateRegistry,
     operations::{Gate, Operation},
+    parameter::Parameter,
+    register::{ClassicalRegister, QuantumRegister},
 };
 
+/// What the parser does with an instruction whose name is neither a known gate
+/// nor one of the built-in operations.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub enum UnknownOperationPolicy {
+    /// Fail with [`ParseErrorKind::UnknownOperation`](crate::error::ParseErrorKind).
+    Error,
+    /// Keep the instruction as an [`Operation::Opaque`].
+    #[default]
+    Opaque,
+    /// Keep the instruction as an [`Operation::Opaque`] and record a warning,
+    /// available from [`QuantumCircuit::warnings`].
+    Warn,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct QuantumCircuit {
     instr: Vec<CircuitInstruction>,
     qubits: Vec<Qubit>,
     clbits: Vec<Clbit>,
+    ancillas: Vec<AncillaQubit>,
+    qregs: Vec<QuantumRegister>,
+    cregs: Vec<ClassicalRegister>,
+    warnings: Vec<ParseError>,
 }
 
 impl QuantumCircuit {
+    /// Parse a circuit from the repr of Qiskit's `QuantumCircuit.data`.
+    ///
+    /// Panics if the input cannot be parsed; use [`QuantumCircuit::try_new`] to
+    /// handle malformed input gracefully.
     pub fn new(input: String, custom_gates: Option<Vec<Gate>>) -> Self {
-        let mut operations: Vec<Operation> = Vec::new();
-        if let Some(c_gates) = custom_gates {
-            operations.extend(c_gates.into_iter().map(|gate| Operation::Gate(gate)));
+        Self::try_new(input, custom_gates).unwrap_or_else(|err| panic!("{}", err))
+    }
+
+    /// Parse a circuit from the repr of Qiskit's `QuantumCircuit.data`,
+    /// returning a [`ParseError`] instead of panicking on malformed input.
+    /// Unknown instructions are kept as [`Operation::Opaque`].
+    pub fn try_new(input: String, custom_gates: Option<Vec<Gate>>) -> Result<Self, ParseError> {
+        Self::try_new_with_policy(input, custom_gates, UnknownOperationPolicy::default())
+    }
+
+    /// Like [`QuantumCircuit::try_new`], choosing how instructions that are not
+    /// known gates or built-in operations are handled.
+    pub fn try_new_with_policy(
+        input: String,
+        custom_gates: Option<Vec<Gate>>,
+        policy: UnknownOperationPolicy,
+    ) -> Result<Self, ParseError> {
+        // user supplied gates take precedence over the built-in ones
+        let mut registry = GateRegistry::standard();
+        for gate in custom_gates.unwrap_or_default() {
+            registry.register_gate(gate);
         }
+        Self::try_new_with_registry(input, registry, policy)
+    }
 
-        let mut parser = parser::Parser::new(input);
+    /// Parse a circuit, resolving gates by name through `registry` instead of
+    /// the standard library.
+    pub fn try_new_with_registry(
+        input: String,
+        registry: GateRegistry,
+        policy: UnknownOperationPolicy,
+    ) -> Result<Self, ParseError> {
+        let mut parser = parser::Parser::new(input, registry, policy)?;
         let mut qubits: Vec<Qubit> = vec![];
         let mut clbits: Vec<Clbit> = vec![];
+        let mut ancillas: Vec<AncillaQubit> = vec![];
 
         let instr: Vec<CircuitInstruction> =
-            parser.parse(&mut operations, &mut qubits, &mut clbits);
+            parser.parse(&mut qubits, &mut clbits, &mut ancillas)?;
 
-        QuantumCircuit {
+        let mut circuit = QuantumCircuit {
             instr,
-            qubits,
-            clbits,
-        }
+            qubits: vec![],
+            clbits: vec![],
+            ancillas,
+            qregs: vec![],
+            cregs: vec![],
+            warnings: parser.warnings().clone(),
+        };
+        qubits.into_iter().for_each(|qubit| circuit.add_qubit(qubit));
+        clbits.into_iter().for_each(|clbit| circuit.add_clbit(clbit));
+
+        Ok(circuit)
     }
 
     pub fn instructions(&self) -> &Vec<CircuitInstruction> {
@@ -53,10 +119,136 @@ impl QuantumCircuit {
         &self.clbits
     }
 
+    /// Get the ancilla qubits of the circuit. These are also included in
+    /// [`QuantumCircuit::qubits`].
+    pub fn ancillas(&self) -> &Vec<AncillaQubit> {
+        &self.ancillas
+    }
+
+    /// The symbolic parameters of the circuit, deduplicated and sorted like
+    /// Qiskit's `QuantumCircuit.parameters`.
+    pub fn parameters(&self) -> Vec<Parameter> {
+        let mut parameters: Vec<Parameter> = self
+            .instr
+            .iter()
+            .flat_map(|instr| match instr.operation() {
+                Operation::Gate(gate) => gate.params().iter().flat_map(|p| p.parameters()).collect(),
+                Operation::Opaque(opaque) => {
+                    opaque.params().iter().flat_map(|p| p.parameters()).collect()
+                }
+                _ => vec![],
+            })
+            .collect();
+        parameters.sort();
+        parameters.dedup();
+        parameters
+    }
+
+    /// Return a copy of the circuit with the given values substituted for its
+    /// parameters. Gate matrices are recomputed for gates whose parameters
+    /// become fully bound; parameters without a value stay symbolic.
+    pub fn assign_parameters(
+        &self,
+        values: &HashMap<Parameter, f64>,
+    ) -> Result<QuantumCircuit, ParameterError> {
+        let parameters = self.parameters();
+        if let Some(unknown) = values.keys().find(|p| !parameters.contains(p)) {
+            return Err(ParameterError::UnknownParameter(unknown.clone()));
+        }
+
+        let instr = self
+            .instr
+            .iter()
+            .map(|instr| {
+                let operation = match instr.operation() {
+                    Operation::Gate(gate) => Operation::Gate(gate.bind(values)),
+                    Operation::Opaque(opaque) => Operation::Opaque(opaque.bind(values)),
+                    operation => operation.clone(),
+                };
+                CircuitInstruction::new(operation, instr.qubits().clone(), instr.clbits().clone())
+            })
+            .collect();
+
+        Ok(QuantumCircuit {
+            instr,
+            ..self.clone()
+        })
+    }
+
+    /// Like [`QuantumCircuit::assign_parameters`], taking one value per
+    /// parameter in the order of [`QuantumCircuit::parameters`].
+    pub fn assign_parameters_by_position(
+        &self,
+        values: &[f64],
+    ) -> Result<QuantumCircuit, ParameterError> {
+        let parameters = self.parameters();
+        if parameters.len() != values.len() {
+            return Err(ParameterError::LengthMismatch {
+                expected: parameters.len(),
+                found: values.len(),
+            });
+        }
+        let values: HashMap<Parameter, f64> =
+            parameters.into_iter().zip(values.iter().copied()).collect();
+        self.assign_parameters(&values)
+    }
+
+    /// Unknown operations encountered while parsing under
+    /// [`UnknownOperationPolicy::Warn`].
+    pub fn warnings(&self) -> &Vec<ParseError> {
+        &self.warnings
+    }
+
+    /// Get the quantum registers of the circuit, in the order their first bit
+    /// was seen.
+    pub fn qregs(&self) -> &Vec<QuantumRegister> {
+        &self.qregs
+    }
+
+    /// Get the classical registers of the circuit, in the order their first
+    /// bit was seen.
+    pub fn cregs(&self) -> &Vec<ClassicalRegister> {
+        &self.cregs
+    }
+
+    /// Look up a quantum register by name.
+    pub fn qreg(&self, name: &str) -> Option<&QuantumRegister> {
+        self.qregs.iter().find(|reg| reg.name() == name)
+    }
+
+    /// Look up a classical register by name.
+    pub fn creg(&self, name: &str) -> Option<&ClassicalRegister> {
+        self.cregs.iter().find(|reg| reg.name() == name)
+    }
+
+    /// Locate a qubit in the circuit, mirroring Qiskit's `find_bit`. Returns
+    /// the register containing it, its index within that register and its
+    /// position in [`QuantumCircuit::qubits`].
+    pub fn find_bit(&self, qubit: &Qubit) -> Option<(&QuantumRegister, usize, usize)> {
+        let position = self.qubits.iter().position(|bit| bit == qubit)?;
+        self.qregs.iter().find_map(|reg| {
+            reg.index_of(qubit).map(|index| (reg, index, position))
+        })
+    }
+
+    /// Locate a clbit in the circuit. Returns the register containing it, its
+    /// index within that register and its position in
+    /// [`QuantumCircuit::clbits`].
+    pub fn find_clbit(&self, clbit: &Clbit) -> Option<(&ClassicalRegister, usize, usize)> {
+        let position = self.clbits.iter().position(|bit| bit == clbit)?;
+        self.cregs.iter().find_map(|reg| {
+            reg.index_of(clbit).map(|index| (reg, index, position))
+        })
+    }
+
     pub fn add_qubit(&mut self, qubit: Qubit) {
         if self.qubits.contains(&qubit) {
             return;
         }
+        if self.qreg(&qubit.name()).is_none() {
+            self.qregs
+                .push(QuantumRegister::new(qubit.name(), qubit.register_size()));
+        }
         self.qubits.push(qubit);
     }
 
@@ -64,13 +256,24 @@ impl QuantumCircuit {
         if self.clbits.contains(&clbit) {
             return;
         }
+        if self.creg(&clbit.name()).is_none() {
+            self.cregs
+                .push(ClassicalRegister::new(clbit.name(), clbit.register_size()));
+        }
         self.clbits.push(clbit);
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::get_first)]
 mod tests {
+    use crate::error::ParseErrorKind;
     use crate::gates::singleton as singleton_gates;
+    use crate::gates::{ion, parameterized};
+    use crate::c64;
+    use crate::operations::{Barrier, Delay, GateBuilder, Measurement, Opaque, Reset, TimeUnit};
+    use nalgebra::{DMatrix, DVector};
+    use crate::parameter::{Param, Parameter, ParameterExpression};
 
     use super::*;
 
@@ -117,38 +320,611 @@ mod tests {
         ));
     }
 
-    // /// Testing a CNOT gate
-    // #[test]
-    // fn test_two_qubit_one_gate() {
-    //     let input = "[CircuitInstruction(operation=Instruction(name='cx', num_qubits=2, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(2, 'q'), 0), Qubit(QuantumRegister(2, 'q'), 1)), clbits=())]";
+    /// Parameters may be negative, use exponents or be NumPy scalars
+    #[test]
+    fn test_signed_params() {
+        let input = "[CircuitInstruction(operation=Instruction(name='u', num_qubits=1, num_clbits=0, params=[-1.5707963267948966, 1e-07, np.float64(0.5)]), qubits=(Qubit(QuantumRegister(1, 'q'), 0),), clbits=())]";
+
+        let qc = QuantumCircuit::new(input.to_string(), None);
 
-    //     let qc = QuantumCircuit::new(input.to_string(), None);
+        let Operation::Gate(gate) = qc.instructions()[0].operation() else {
+            panic!("expected a gate");
+        };
+        assert_eq!(gate.params(), &vec![
+            Param::Value(-std::f64::consts::FRAC_PI_2),
+            Param::Value(1e-07),
+            Param::Value(0.5),
+        ]);
+    }
 
-    //     let instructions = qc.get_instructions();
-    //     assert_eq!(instructions.len(), 1);
+    /// Malformed input is reported as an error rather than a panic
+    #[test]
+    fn test_try_new_unexpected_token() {
+        let input = "[CircuitInstruction(operation=Gate(name='x'))]";
+
+        let err = QuantumCircuit::try_new(input.to_string(), None).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::UnexpectedToken {
+            expected: format!("{:?}", Token::Identifier("Instruction".to_string())),
+            found: Token::Identifier("Gate".to_string()),
+        });
+        assert_eq!(err.span(), Span::new(30, 34));
+        assert_eq!(err.instruction(), Some(0));
+    }
 
-    //     let instr = instructions.get(0).unwrap();
-    //     assert_eq!(instr, &CircuitInstruction::new(
-    //         Operation::Gate(singleton_gates::cx()),
-    //         vec![0, 1],
-    //         vec![],
-    //     ));
+    /// Truncated input is reported as an unexpected end of input
+    #[test]
+    fn test_try_new_unexpected_eof() {
+        let input = "[CircuitInstruction(operation=Instruction(name='x', num_qubits=1";
 
-    // }
+        let err = QuantumCircuit::try_new(input.to_string(), None).unwrap_err();
+        assert!(matches!(err.kind(), ParseErrorKind::UnexpectedEof { .. }));
+        assert_eq!(err.span(), Span::new(input.len(), input.len()));
+    }
 
-    // /// Testing the Bell state circuit
-    // #[test]
-    // fn test_bell_state() {
-    //     let input = "[CircuitInstruction(operation=Instruction(name='h', num_qubits=1, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(2, 'q'), 0),), clbits=()), CircuitInstruction(operation=Instruction(name='cx', num_qubits=2, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(2, 'q'), 0), Qubit(QuantumRegister(2, 'q'), 1)), clbits=())]";
+    /// Unsupported bit constructors are rejected
+    #[test]
+    fn test_try_new_unknown_bit_type() {
+        let input = "[CircuitInstruction(operation=Instruction(name='x', num_qubits=1, num_clbits=0, params=[]), qubits=(Foo(QuantumRegister(1, 'q'), 0),), clbits=())]";
 
-    //     let parser = QuantumCircuit::new(input.to_string());
-    // }
+        let err = QuantumCircuit::try_new(input.to_string(), None).unwrap_err();
+        assert!(matches!(err.kind(), ParseErrorKind::UnknownBitType { found } if found == "Foo"));
+    }
+
+    /// Numbers with more than one decimal point are rejected by the tokenizer
+    #[test]
+    fn test_try_new_invalid_number() {
+        let input = "[CircuitInstruction(operation=Instruction(name='rx', num_qubits=1, num_clbits=0, params=[1.2.3]), qubits=(), clbits=())]";
 
-    // /// Testing the classic |0> -> |000> QECC circuit
-    // #[test]
-    // fn test_naive_qecc() {
-    //     let input = "[CircuitInstruction(operation=Instruction(name='cx', num_qubits=2, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(3, 'q'), 0), Qubit(QuantumRegister(3, 'q'), 1)), clbits=()), CircuitInstruction(operation=Instruction(name='cx', num_qubits=2, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(3, 'q'), 0), Qubit(QuantumRegister(3, 'q'), 2)), clbits=())]";
+        let err = QuantumCircuit::try_new(input.to_string(), None).unwrap_err();
+        assert!(matches!(err.kind(), ParseErrorKind::InvalidNumber { literal } if literal == "1.2.3"));
+        assert_eq!(err.instruction(), None);
+    }
 
-    //     let parser = QuantumCircuit::new(input.to_string());
-    // }
+    /// Register sizes and bit indices must be non-negative integers
+    #[test]
+    fn test_try_new_invalid_bit() {
+        let bit = |size: &str, index: &str| {
+            format!("[CircuitInstruction(operation=Instruction(name='x', num_qubits=1, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister({}, 'q'), {}),), clbits=())]", size, index)
+        };
+        for (size, index, literal) in [
+            ("1", "-1", "-1"),
+            ("1", "2.5", "2.5"),
+            ("nan", "0", "nan"),
+            ("1", "1e30", "1e30"),
+        ] {
+            let input = bit(size, index);
+            let err = QuantumCircuit::try_new(input.clone(), None).unwrap_err();
+            assert!(
+                matches!(err.kind(), ParseErrorKind::InvalidNumber { literal: found } if found == literal),
+                "{:?}",
+                err
+            );
+            let start = input.find(literal).unwrap();
+            assert_eq!(err.span().start, start);
+        }
+    }
+
+    /// Errors point at the failing instruction and underline the bad token
+    #[test]
+    fn test_try_new_error_snippet() {
+        let input = "[CircuitInstruction(operation=Instruction(name='x', num_qubits=1, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(1, 'q'), 0),), clbits=()), CircuitInstruction(operation=Instruction(name='y', num_qubits=1, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(1, 'q'), 0),), clbuts=())]";
+
+        let err = QuantumCircuit::try_new(input.to_string(), None).unwrap_err();
+        assert_eq!(err.instruction(), Some(1));
+
+        let snippet = err.snippet().unwrap();
+        let (line, caret) = snippet.split_once('\n').unwrap();
+        let caret_start = caret.find('^').unwrap();
+        assert_eq!(&line[caret_start..caret.len()], "clbuts");
+        assert!(err.to_string().contains("in instruction 1"));
+    }
+
+    /// Testing a CNOT gate
+    #[test]
+    fn test_two_qubit_one_gate() {
+        let input = "[CircuitInstruction(operation=Instruction(name='cx', num_qubits=2, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(2, 'q'), 0), Qubit(QuantumRegister(2, 'q'), 1)), clbits=())]";
+
+        let qc = QuantumCircuit::new(input.to_string(), None);
+
+        let instructions = qc.instructions();
+        assert_eq!(instructions.len(), 1);
+
+        let instr = instructions.first().unwrap();
+        assert_eq!(instr, &CircuitInstruction::new(
+            Operation::Gate(singleton_gates::cx()),
+            vec![0, 1],
+            vec![],
+        ));
+    }
+
+    /// Testing the Bell state circuit
+    #[test]
+    fn test_bell_state() {
+        let input = "[CircuitInstruction(operation=Instruction(name='h', num_qubits=1, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(2, 'q'), 0),), clbits=()), CircuitInstruction(operation=Instruction(name='cx', num_qubits=2, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(2, 'q'), 0), Qubit(QuantumRegister(2, 'q'), 1)), clbits=())]";
+
+        let qc = QuantumCircuit::new(input.to_string(), None);
+
+        let instructions = qc.instructions();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[0].operation(), &Operation::Gate(singleton_gates::hadamard()));
+        assert_eq!(instructions[1].operation(), &Operation::Gate(singleton_gates::cx()));
+        assert_eq!(instructions[1].qubits(), &vec![0, 1]);
+        assert_eq!(qc.qubits().len(), 2);
+    }
+
+    /// Testing a barrier across three qubits, with a trailing comma
+    #[test]
+    fn test_barrier_many_qubits() {
+        let input = "[CircuitInstruction(operation=Instruction(name='barrier', num_qubits=3, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(3, 'q'), 0), Qubit(QuantumRegister(3, 'q'), 1), Qubit(QuantumRegister(3, 'q'), 2),), clbits=())]";
+
+        let qc = QuantumCircuit::new(input.to_string(), None);
+
+        assert_eq!(qc.instructions()[0].qubits().len(), 3);
+        assert_eq!(qc.qubits().len(), 3);
+    }
+
+    /// Testing a measurement into a classical register
+    #[test]
+    fn test_classical_register() {
+        let input = "[CircuitInstruction(operation=Instruction(name='measure', num_qubits=1, num_clbits=1, params=[]), qubits=(Qubit(QuantumRegister(2, 'q'), 1),), clbits=(Clbit(ClassicalRegister(2, 'c'), 1),))]";
+
+        let qc = QuantumCircuit::new(input.to_string(), None);
+
+        // like Qiskit, the circuit holds every bit of the registers it uses
+        assert_eq!(qc.clbits(), &vec![Clbit::new("c".to_string(), 2, 0), Clbit::new("c".to_string(), 2, 1)]);
+        assert_eq!(qc.qubits(), &vec![Qubit::new("q".to_string(), 2, 0), Qubit::new("q".to_string(), 2, 1)]);
+        assert_eq!(qc.instructions()[0].qubits(), &vec![1]);
+        assert_eq!(qc.instructions()[0].clbits(), &vec![1]);
+    }
+
+    /// Testing ancilla qubits, which are also qubits of the circuit
+    #[test]
+    fn test_ancilla_register() {
+        let input = "[CircuitInstruction(operation=Instruction(name='cx', num_qubits=2, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(1, 'q'), 0), AncillaQubit(AncillaRegister(1, 'anc'), 0)), clbits=())]";
+
+        let qc = QuantumCircuit::new(input.to_string(), None);
+
+        assert_eq!(qc.ancillas(), &vec![AncillaQubit::new("anc".to_string(), 1, 0)]);
+        assert_eq!(qc.qubits().len(), 2);
+        assert_eq!(qc.qubits()[1].name(), "anc");
+    }
+
+    /// Classical bits are rejected among an instruction's qubits
+    #[test]
+    fn test_clbit_in_qubits() {
+        let input = "[CircuitInstruction(operation=Instruction(name='x', num_qubits=1, num_clbits=0, params=[]), qubits=(Clbit(ClassicalRegister(1, 'c'), 0),), clbits=())]";
+
+        let err = QuantumCircuit::try_new(input.to_string(), None).unwrap_err();
+        assert!(matches!(err.kind(), ParseErrorKind::UnexpectedBitType { group, .. } if group == "qubits"));
+    }
+
+    /// Testing the classic |0> -> |000> QECC circuit
+    #[test]
+    fn test_naive_qecc() {
+        let input = "[CircuitInstruction(operation=Instruction(name='cx', num_qubits=2, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(3, 'q'), 0), Qubit(QuantumRegister(3, 'q'), 1)), clbits=()), CircuitInstruction(operation=Instruction(name='cx', num_qubits=2, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(3, 'q'), 0), Qubit(QuantumRegister(3, 'q'), 2)), clbits=())]";
+
+        let qc = QuantumCircuit::new(input.to_string(), None);
+
+        let instructions = qc.instructions();
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[0].qubits(), &vec![0, 1]);
+        assert_eq!(instructions[1].qubits(), &vec![0, 2]);
+    }
+
+    /// Bits of different registers with the same local index get distinct
+    /// circuit positions, and operand order is preserved
+    #[test]
+    fn test_multi_register_positions() {
+        let input = "[CircuitInstruction(operation=Instruction(name='h', num_qubits=1, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(1, 'q'), 0),), clbits=()), CircuitInstruction(operation=Instruction(name='cx', num_qubits=2, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(1, 'anc'), 0), Qubit(QuantumRegister(1, 'q'), 0)), clbits=()), CircuitInstruction(operation=Instruction(name='measure', num_qubits=1, num_clbits=1, params=[]), qubits=(Qubit(QuantumRegister(1, 'anc'), 0),), clbits=(Clbit(ClassicalRegister(2, 'c'), 1),))]";
+
+        let qc = QuantumCircuit::new(input.to_string(), None);
+
+        let instructions = qc.instructions();
+        assert_eq!(instructions[0].qubits(), &vec![0]);
+        assert_eq!(instructions[1].qubits(), &vec![1, 0]);
+        assert_eq!(instructions[2].qubits(), &vec![1]);
+        assert_eq!(instructions[2].clbits(), &vec![1]);
+        assert_eq!(qc.qubits()[1].name(), "anc");
+    }
+
+    /// Registers are built from the parsed bits and keep their full size
+    #[test]
+    fn test_registers() {
+        let input = "[CircuitInstruction(operation=Instruction(name='measure', num_qubits=1, num_clbits=1, params=[]), qubits=(Qubit(QuantumRegister(3, 'q'), 2),), clbits=(Clbit(ClassicalRegister(2, 'c'), 1),)), CircuitInstruction(operation=Instruction(name='x', num_qubits=1, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(2, 'ions'), 0),), clbits=())]";
+
+        let qc = QuantumCircuit::new(input.to_string(), None);
+
+        assert_eq!(qc.qregs().len(), 2);
+        let q = qc.qreg("q").unwrap();
+        assert_eq!(q.size(), 3);
+        assert_eq!(q.bits().len(), 3);
+        assert_eq!(qc.creg("c").unwrap().size(), 2);
+        assert!(qc.qreg("c").is_none());
+
+        let ion = Qubit::new("ions".to_string(), 2, 0);
+        let (reg, index, position) = qc.find_bit(&ion).unwrap();
+        assert_eq!(reg.name(), "ions");
+        assert_eq!((index, position), (0, 3));
+
+        let clbit = Clbit::new("c".to_string(), 2, 1);
+        let (reg, index, position) = qc.find_clbit(&clbit).unwrap();
+        assert_eq!(reg.name(), "c");
+        assert_eq!((index, position), (1, 1));
+
+        // part of a register, but never used by the circuit
+        let (reg, index, position) = qc.find_bit(&Qubit::new("q".to_string(), 3, 0)).unwrap();
+        assert_eq!(reg.name(), "q");
+        assert_eq!((index, position), (0, 0));
+    }
+
+    /// Non-gate instructions are parsed into their own operation variants
+    #[test]
+    fn test_non_gate_operations() {
+        let input = "[CircuitInstruction(operation=Instruction(name='reset', num_qubits=1, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(2, 'q'), 1),), clbits=()), CircuitInstruction(operation=Delay(duration=100[unit=dt]), qubits=(Qubit(QuantumRegister(2, 'q'), 0),), clbits=()), CircuitInstruction(operation=Delay(duration=1.5[unit=us]), qubits=(Qubit(QuantumRegister(2, 'q'), 0),), clbits=()), CircuitInstruction(operation=Instruction(name='delay', num_qubits=1, num_clbits=0, params=[20]), qubits=(Qubit(QuantumRegister(2, 'q'), 1),), clbits=()), CircuitInstruction(operation=Instruction(name='barrier', num_qubits=2, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(2, 'q'), 0), Qubit(QuantumRegister(2, 'q'), 1)), clbits=()), CircuitInstruction(operation=Instruction(name='measure', num_qubits=1, num_clbits=1, params=[]), qubits=(Qubit(QuantumRegister(2, 'q'), 0),), clbits=(Clbit(ClassicalRegister(2, 'c'), 1),))]";
+
+        let qc = QuantumCircuit::new(input.to_string(), None);
+
+        let operations: Vec<&Operation> = qc.instructions().iter().map(|instr| instr.operation()).collect();
+        assert_eq!(operations, vec![
+            &Operation::Reset(Reset::new(vec![1])),
+            &Operation::Delay(Delay::new(100.0, TimeUnit::DT)),
+            &Operation::Delay(Delay::new(1.5, TimeUnit::US)),
+            &Operation::Delay(Delay::new(20.0, TimeUnit::DT)),
+            &Operation::Barrier(Barrier::new(vec![0, 1])),
+            &Operation::Measurement(Measurement::new(vec![(0, 1)])),
+        ]);
+    }
+
+    /// Named delays take their unit like `Delay`; measurements pair every
+    /// qubit with a clbit
+    #[test]
+    fn test_delay_and_measure_operands() {
+        let delay = |params: &str| {
+            format!("[CircuitInstruction(operation=Instruction(name='delay', num_qubits=1, num_clbits=0, params=[{}]), qubits=(Qubit(QuantumRegister(1, 'q'), 0),), clbits=())]", params)
+        };
+        let qc = QuantumCircuit::new(delay("2.5[unit=us]"), None);
+        assert_eq!(qc.instructions()[0].operation(), &Operation::Delay(Delay::new(2.5, TimeUnit::US)));
+
+        let err = QuantumCircuit::try_new(delay("Parameter(t)"), None).unwrap_err();
+        assert!(matches!(err.kind(), ParseErrorKind::NonNumericDuration { found } if found == "t"));
+        let err = QuantumCircuit::try_new(delay("1.0[unit=fortnight]"), None).unwrap_err();
+        assert!(matches!(err.kind(), ParseErrorKind::UnexpectedToken { .. }));
+
+        let input = "[CircuitInstruction(operation=Instruction(name='measure', num_qubits=2, num_clbits=1, params=[]), qubits=(Qubit(QuantumRegister(2, 'q'), 0), Qubit(QuantumRegister(2, 'q'), 1)), clbits=(Clbit(ClassicalRegister(1, 'c'), 0),))]";
+        let err = QuantumCircuit::try_new(input.to_string(), None).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::MeasureOperandMismatch { qubits: 2, clbits: 1 });
+        assert_eq!(err.instruction(), Some(0));
+    }
+
+    /// Unknown instructions are kept opaque by default
+    #[test]
+    fn test_unknown_operation_opaque() {
+        let input = "[CircuitInstruction(operation=Instruction(name='mystery', num_qubits=2, num_clbits=0, params=[0.5]), qubits=(Qubit(QuantumRegister(2, 'q'), 0), Qubit(QuantumRegister(2, 'q'), 1)), clbits=())]";
+
+        let qc = QuantumCircuit::new(input.to_string(), None);
+
+        assert_eq!(
+            qc.instructions()[0].operation(),
+            &Operation::Opaque(Opaque::new("mystery".to_string(), 2, 0, vec![Param::Value(0.5)]))
+        );
+        assert!(qc.warnings().is_empty());
+    }
+
+    /// Unknown instructions can be rejected or reported
+    #[test]
+    fn test_unknown_operation_policies() {
+        let input = "[CircuitInstruction(operation=Instruction(name='x', num_qubits=1, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(1, 'q'), 0),), clbits=()), CircuitInstruction(operation=Instruction(name='mystery', num_qubits=1, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(1, 'q'), 0),), clbits=())]";
+
+        let err = QuantumCircuit::try_new_with_policy(input.to_string(), None, UnknownOperationPolicy::Error)
+            .unwrap_err();
+        assert!(matches!(err.kind(), ParseErrorKind::UnknownOperation { name } if name == "mystery"));
+        assert_eq!(err.instruction(), Some(1));
+
+        let qc = QuantumCircuit::try_new_with_policy(input.to_string(), None, UnknownOperationPolicy::Warn)
+            .unwrap();
+        assert_eq!(qc.warnings().len(), 1);
+        assert_eq!(qc.warnings()[0].instruction(), Some(1));
+        assert!(qc.warnings()[0].snippet().unwrap().contains("'mystery'"));
+        assert!(matches!(qc.instructions()[1].operation(), Operation::Opaque(_)));
+    }
+
+    /// Custom gates are used in place of opaque operations
+    #[test]
+    fn test_custom_gate() {
+        let input = "[CircuitInstruction(operation=Instruction(name='mystery', num_qubits=1, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(1, 'q'), 0),), clbits=())]";
+        let custom = singleton_gates::x().builder().name("mystery".to_string()).build();
+
+        let qc = QuantumCircuit::try_new_with_policy(input.to_string(), Some(vec![custom.clone()]), UnknownOperationPolicy::Error)
+            .unwrap();
+
+        assert_eq!(qc.instructions()[0].operation(), &Operation::Gate(custom));
+    }
+
+    /// Declared operand counts and gate matrices are checked, and every
+    /// mismatch is reported
+    #[test]
+    fn test_validation_mismatches() {
+        let input = "[CircuitInstruction(operation=Instruction(name='cx', num_qubits=2, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(2, 'q'), 0),), clbits=()), CircuitInstruction(operation=Instruction(name='h', num_qubits=1, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(2, 'q'), 0),), clbits=()), CircuitInstruction(operation=Instruction(name='mystery', num_qubits=2, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(2, 'q'), 0), Qubit(QuantumRegister(2, 'q'), 1)), clbits=())]";
+        let custom = singleton_gates::x().builder().name("mystery".to_string()).build();
+
+        let err = QuantumCircuit::try_new(input.to_string(), Some(vec![custom])).unwrap_err();
+        let ParseErrorKind::Validation { errors } = err.kind() else {
+            panic!("expected a validation error, got {:?}", err.kind());
+        };
+
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].kind(), &ParseErrorKind::OperandCountMismatch {
+            name: "cx".to_string(),
+            group: "qubits".to_string(),
+            declared: 2,
+            found: 1,
+        });
+        assert_eq!(errors[0].instruction(), Some(0));
+        // the cx matrix is fine for its declared qubits
+        assert!(matches!(errors[1].kind(), ParseErrorKind::MatrixDimensionMismatch { name, .. } if name == "mystery"));
+        assert_eq!(errors[1].instruction(), Some(2));
+        assert!(err.to_string().contains("'mystery' acts on 2 qubit(s) but its matrix is 2x2"));
+    }
+
+    /// Symbolic parameters and expressions are parsed from the repr
+    #[test]
+    fn test_symbolic_params() {
+        let input = "[CircuitInstruction(operation=Instruction(name='custom', num_qubits=1, num_clbits=0, params=[Parameter(theta), ParameterExpression(2*theta + 0.5), ParameterVectorElement(phi[1]), ParameterExpression(-sin(theta)**2/pi - 1.0)]), qubits=(Qubit(QuantumRegister(1, 'q'), 0),), clbits=())]";
+
+        let qc = QuantumCircuit::new(input.to_string(), None);
+
+        let Operation::Opaque(opaque) = qc.instructions()[0].operation() else {
+            panic!("expected an opaque operation");
+        };
+        let theta = Parameter::new("theta".to_string());
+        let params = opaque.params();
+        assert_eq!(params[0], Param::Expression(ParameterExpression::Symbol(theta.clone())));
+        assert_eq!(params[2].parameters(), vec![Parameter::new("phi[1]".to_string())]);
+        assert_eq!(params[1].parameters(), vec![theta.clone()]);
+
+        let values = HashMap::from([(theta, 0.25)]);
+        assert_eq!(params[1].bind(&values), Param::Value(1.0));
+
+        let expected = -(0.25_f64.sin().powi(2)) / std::f64::consts::PI - 1.0;
+        let bound = params[3].bind(&values).value().unwrap();
+        assert!((bound - expected).abs() < 1e-12);
+    }
+
+    /// Parameters may be named like the special floats
+    #[test]
+    fn test_special_float_parameter_names() {
+        let input = "[CircuitInstruction(operation=Instruction(name='custom', num_qubits=1, num_clbits=0, params=[Parameter(inf), Parameter(nan), inf]), qubits=(Qubit(QuantumRegister(1, 'q'), 0),), clbits=())]";
+
+        let qc = QuantumCircuit::new(input.to_string(), None);
+
+        let Operation::Opaque(opaque) = qc.instructions()[0].operation() else {
+            panic!("expected an opaque operation");
+        };
+        let symbol = |name: &str| {
+            Param::Expression(ParameterExpression::Symbol(Parameter::new(name.to_string())))
+        };
+        assert_eq!(opaque.params()[0], symbol("inf"));
+        assert_eq!(opaque.params()[1], symbol("nan"));
+        assert_eq!(opaque.params()[2], Param::Value(f64::INFINITY));
+    }
+
+    /// Parameters left unbound keep the expression symbolic
+    #[test]
+    fn test_partial_binding() {
+        let input = "[CircuitInstruction(operation=Instruction(name='custom', num_qubits=1, num_clbits=0, params=[ParameterExpression(a*b-2)]), qubits=(Qubit(QuantumRegister(1, 'q'), 0),), clbits=())]";
+
+        let qc = QuantumCircuit::new(input.to_string(), None);
+
+        let Operation::Opaque(opaque) = qc.instructions()[0].operation() else {
+            panic!("expected an opaque operation");
+        };
+        let a = Parameter::new("a".to_string());
+        let b = Parameter::new("b".to_string());
+        let param = &opaque.params()[0];
+        assert_eq!(param.parameters(), vec![a.clone(), b.clone()]);
+
+        let partial = param.bind(&HashMap::from([(a, 3.0)]));
+        assert_eq!(partial.value(), None);
+        assert_eq!(partial.parameters(), vec![b.clone()]);
+        assert_eq!(partial.bind(&HashMap::from([(b, 2.0)])), Param::Value(4.0));
+    }
+
+    /// Parameters are listed once, in Qiskit's order
+    #[test]
+    fn test_parameters() {
+        let input = "[CircuitInstruction(operation=Instruction(name='custom', num_qubits=1, num_clbits=0, params=[ParameterVectorElement(v[10]), Parameter(b), ParameterExpression(a + v[2])]), qubits=(Qubit(QuantumRegister(1, 'q'), 0),), clbits=()), CircuitInstruction(operation=Instruction(name='custom', num_qubits=1, num_clbits=0, params=[Parameter(b)]), qubits=(Qubit(QuantumRegister(1, 'q'), 0),), clbits=())]";
+
+        let qc = QuantumCircuit::new(input.to_string(), None);
+
+        let names: Vec<String> = qc.parameters().iter().map(|p| p.name().clone()).collect();
+        assert_eq!(names, vec!["a", "b", "v[2]", "v[10]"]);
+    }
+
+    /// Binding recomputes the matrices of parameterized custom gates
+    #[test]
+    fn test_assign_parameters() {
+        fn phase(params: &[f64]) -> DMatrix<c64> {
+            DMatrix::from_diagonal(&DVector::from_vec(vec![
+                c64::new(1.0, 0.0),
+                c64::from_polar(1.0, params[0]),
+            ]))
+        }
+        let custom = GateBuilder::new()
+            .name("phase".to_string())
+            .params(vec![Param::Value(0.0)])
+            .unit(TimeUnit::DT)
+            .matrix_fn(phase)
+            .build();
+        let input = "[CircuitInstruction(operation=Instruction(name='phase', num_qubits=1, num_clbits=0, params=[ParameterExpression(2*theta)]), qubits=(Qubit(QuantumRegister(1, 'q'), 0),), clbits=()), CircuitInstruction(operation=Instruction(name='x', num_qubits=1, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(1, 'q'), 0),), clbits=())]";
+
+        let qc = QuantumCircuit::new(input.to_string(), Some(vec![custom]));
+        let theta = Parameter::new("theta".to_string());
+
+        for value in [0.1, 0.7] {
+            let bound = qc.assign_parameters_by_position(&[value]).unwrap();
+            let gate = Gate::from(bound.instructions()[0].operation().clone());
+            assert_eq!(gate.params(), &vec![Param::Value(2.0 * value)]);
+            assert_eq!(gate.to_matrix(), phase(&[2.0 * value]));
+            assert_eq!(bound.instructions()[1], qc.instructions()[1]);
+            assert!(bound.parameters().is_empty());
+
+            let by_name = qc.assign_parameters(&HashMap::from([(theta.clone(), value)])).unwrap();
+            assert_eq!(by_name, bound);
+        }
+        // the original circuit stays symbolic
+        assert_eq!(qc.parameters(), vec![theta]);
+    }
+
+    /// Standard parameterized gates get their matrix from their parameters
+    #[test]
+    fn test_parameterized_gates() {
+        let input = "[CircuitInstruction(operation=Instruction(name='rx', num_qubits=1, num_clbits=0, params=[1.5707963267948966]), qubits=(Qubit(QuantumRegister(2, 'q'), 0),), clbits=()), CircuitInstruction(operation=Instruction(name='crz', num_qubits=2, num_clbits=0, params=[Parameter(theta)]), qubits=(Qubit(QuantumRegister(2, 'q'), 0), Qubit(QuantumRegister(2, 'q'), 1)), clbits=())]";
+
+        let qc = QuantumCircuit::new(input.to_string(), None);
+
+        let rx = Gate::from(qc.instructions()[0].operation().clone());
+        assert_eq!(rx, parameterized::rx(std::f64::consts::FRAC_PI_2));
+
+        let bound = qc.assign_parameters_by_position(&[0.4]).unwrap();
+        let crz = Gate::from(bound.instructions()[1].operation().clone());
+        assert_eq!(crz.to_matrix(), parameterized::crz(0.4).to_matrix());
+
+        let input = "[CircuitInstruction(operation=Instruction(name='u', num_qubits=1, num_clbits=0, params=[0.1]), qubits=(Qubit(QuantumRegister(1, 'q'), 0),), clbits=())]";
+        let err = QuantumCircuit::try_new(input.to_string(), None).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::InvalidParams {
+            name: "u".to_string(),
+            expected: 3,
+            found: 1,
+        });
+    }
+
+    /// The fixed standard gates of transpiled circuits are recognized
+    #[test]
+    fn test_standard_gates() {
+        let input = "[CircuitInstruction(operation=Instruction(name='sx', num_qubits=1, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(4, 'q'), 0),), clbits=()), CircuitInstruction(operation=Instruction(name='ecr', num_qubits=2, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(4, 'q'), 1), Qubit(QuantumRegister(4, 'q'), 0)), clbits=()), CircuitInstruction(operation=Instruction(name='cswap', num_qubits=3, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(4, 'q'), 0), Qubit(QuantumRegister(4, 'q'), 1), Qubit(QuantumRegister(4, 'q'), 2)), clbits=()), CircuitInstruction(operation=Instruction(name='mcx', num_qubits=4, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(4, 'q'), 0), Qubit(QuantumRegister(4, 'q'), 1), Qubit(QuantumRegister(4, 'q'), 2), Qubit(QuantumRegister(4, 'q'), 3)), clbits=())]";
+
+        let qc = QuantumCircuit::try_new_with_policy(input.to_string(), None, UnknownOperationPolicy::Error)
+            .unwrap();
+
+        let operations: Vec<&Operation> = qc.instructions().iter().map(|i| i.operation()).collect();
+        assert_eq!(operations, vec![
+            &Operation::Gate(singleton_gates::sx()),
+            &Operation::Gate(singleton_gates::ecr()),
+            &Operation::Gate(singleton_gates::cswap()),
+            &Operation::Gate(singleton_gates::c3x()),
+        ]);
+        assert_eq!(qc.instructions()[1].qubits(), &vec![1, 0]);
+    }
+
+    /// Qiskit's relative-phase Toffolis are named `rccx` and `rcccx`
+    #[test]
+    fn test_relative_phase_toffoli() {
+        let input = "[CircuitInstruction(operation=Instruction(name='rccx', num_qubits=3, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(4, 'q'), 0), Qubit(QuantumRegister(4, 'q'), 1), Qubit(QuantumRegister(4, 'q'), 2)), clbits=()), CircuitInstruction(operation=Instruction(name='rcccx', num_qubits=4, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(4, 'q'), 0), Qubit(QuantumRegister(4, 'q'), 1), Qubit(QuantumRegister(4, 'q'), 2), Qubit(QuantumRegister(4, 'q'), 3)), clbits=())]";
+
+        let qc = QuantumCircuit::try_new_with_policy(input.to_string(), None, UnknownOperationPolicy::Error)
+            .unwrap();
+
+        let operations: Vec<&Operation> = qc.instructions().iter().map(|i| i.operation()).collect();
+        assert_eq!(operations, vec![
+            &Operation::Gate(singleton_gates::rccx()),
+            &Operation::Gate(singleton_gates::rc3x()),
+        ]);
+    }
+
+    /// Ion-native gates are recognized under the names ion providers emit,
+    /// with `ms` resolved by its number of parameters
+    #[test]
+    fn test_ion_gates() {
+        let input = "[CircuitInstruction(operation=Instruction(name='gpi2', num_qubits=1, num_clbits=0, params=[0.25]), qubits=(Qubit(QuantumRegister(3, 'q'), 0),), clbits=()), CircuitInstruction(operation=Instruction(name='ms', num_qubits=2, num_clbits=0, params=[0, 0.5, Parameter(theta)]), qubits=(Qubit(QuantumRegister(3, 'q'), 0), Qubit(QuantumRegister(3, 'q'), 1)), clbits=()), CircuitInstruction(operation=Instruction(name='ms', num_qubits=3, num_clbits=0, params=[1.5707963267948966]), qubits=(Qubit(QuantumRegister(3, 'q'), 0), Qubit(QuantumRegister(3, 'q'), 1), Qubit(QuantumRegister(3, 'q'), 2)), clbits=())]";
+
+        let qc = QuantumCircuit::try_new_with_policy(input.to_string(), None, UnknownOperationPolicy::Error)
+            .unwrap();
+        let bound = qc.assign_parameters_by_position(&[0.25]).unwrap();
+
+        let operations: Vec<&Operation> = bound.instructions().iter().map(|i| i.operation()).collect();
+        assert_eq!(operations, vec![
+            &Operation::Gate(ion::gpi2(0.25)),
+            &Operation::Gate(ion::ms(0.0, 0.5, 0.25)),
+            &Operation::Gate(ion::gms(3, std::f64::consts::FRAC_PI_2)),
+        ]);
+
+        let input = "[CircuitInstruction(operation=Instruction(name='ms', num_qubits=2, num_clbits=0, params=[0, 0.5]), qubits=(Qubit(QuantumRegister(2, 'q'), 0), Qubit(QuantumRegister(2, 'q'), 1)), clbits=())]";
+        let err = QuantumCircuit::try_new(input.to_string(), None).unwrap_err();
+        assert_eq!(err.kind(), &ParseErrorKind::InvalidParams {
+            name: "ms".to_string(),
+            expected: 3,
+            found: 2,
+        });
+
+        // the global gate is only built up to `ion::MAX_GMS_QUBITS` qubits
+        let input = "[CircuitInstruction(operation=Instruction(name='ms', num_qubits=11, num_clbits=0, params=[0.5]), qubits=(Qubit(QuantumRegister(11, 'q'), 0), Qubit(QuantumRegister(11, 'q'), 1), Qubit(QuantumRegister(11, 'q'), 2), Qubit(QuantumRegister(11, 'q'), 3), Qubit(QuantumRegister(11, 'q'), 4), Qubit(QuantumRegister(11, 'q'), 5), Qubit(QuantumRegister(11, 'q'), 6), Qubit(QuantumRegister(11, 'q'), 7), Qubit(QuantumRegister(11, 'q'), 8), Qubit(QuantumRegister(11, 'q'), 9), Qubit(QuantumRegister(11, 'q'), 10)), clbits=())]";
+        let err = QuantumCircuit::try_new(input.to_string(), None).unwrap_err();
+        assert!(err.to_string().contains("'ms' acts on 11 qubit(s) but its matrix is 1024x1024"));
+    }
+
+    /// Gates registered by the user resolve to their matrices and
+    /// Hamiltonians, and can replace library gates
+    #[test]
+    fn test_gate_registry() {
+        let input = "[CircuitInstruction(operation=Instruction(name='drive', num_qubits=1, num_clbits=0, params=[0.5]), qubits=(Qubit(QuantumRegister(1, 'q'), 0),), clbits=()), CircuitInstruction(operation=Instruction(name='x', num_qubits=1, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(1, 'q'), 0),), clbits=())]";
+        let mut registry = GateRegistry::standard();
+        registry.register("drive", 1, Some(1), |params: &[f64]| {
+            let gpi2 = ion::with_hamiltonian(&ion::gpi2(params[0]), 10e-6).unwrap();
+            gpi2.builder().name("drive".to_string()).build()
+        });
+        registry.register_gate(singleton_gates::y().builder().name("x".to_string()).build());
+
+        let qc = QuantumCircuit::try_new_with_registry(input.to_string(), registry, UnknownOperationPolicy::Error)
+            .unwrap();
+
+        let drive = Gate::from(qc.instructions()[0].operation().clone());
+        assert_eq!(drive.to_matrix(), ion::gpi2(0.5).to_matrix());
+        let gpi2 = ion::with_hamiltonian(&ion::gpi2(0.5), 10e-6).unwrap();
+        assert_eq!(drive.hamiltonian(), gpi2.hamiltonian());
+        assert!(drive.hamiltonian().is_some());
+        let x = Gate::from(qc.instructions()[1].operation().clone());
+        assert_eq!(x.to_matrix(), singleton_gates::y().to_matrix());
+
+        // without the gate, an empty registry leaves the instruction opaque
+        let qc = QuantumCircuit::try_new_with_registry(input.to_string(), GateRegistry::new(), UnknownOperationPolicy::Opaque)
+            .unwrap();
+        assert!(matches!(qc.instructions()[0].operation(), Operation::Opaque(_)));
+    }
+
+    /// A registry with generators gives every parsed gate a Hamiltonian
+    #[test]
+    fn test_registry_generators() {
+        let input = "[CircuitInstruction(operation=Instruction(name='x', num_qubits=1, num_clbits=0, params=[]), qubits=(Qubit(QuantumRegister(1, 'q'), 0),), clbits=()), CircuitInstruction(operation=Instruction(name='rx', num_qubits=1, num_clbits=0, params=[0.3]), qubits=(Qubit(QuantumRegister(1, 'q'), 0),), clbits=()), CircuitInstruction(operation=Instruction(name='rx', num_qubits=1, num_clbits=0, params=[Parameter(theta)]), qubits=(Qubit(QuantumRegister(1, 'q'), 0),), clbits=())]";
+        let registry = GateRegistry::standard().with_generators(50e-9);
+
+        let qc = QuantumCircuit::try_new_with_registry(input.to_string(), registry, UnknownOperationPolicy::Error)
+            .unwrap();
+        let bound = qc.assign_parameters_by_position(&[0.7]).unwrap();
+
+        for instruction in bound.instructions() {
+            let gate = Gate::from(instruction.operation().clone());
+            assert!(gate.hamiltonian().is_some(), "{}", gate.name());
+            assert!(gate.verify_hamiltonian(1e-9).is_ok(), "{}", gate.name());
+        }
+        let rx = Gate::from(bound.instructions()[2].operation().clone());
+        assert_eq!(rx.to_matrix(), parameterized::rx(0.7).to_matrix());
+    }
+
+    /// Binding rejects unknown parameters and mismatched lengths
+    #[test]
+    fn test_assign_parameters_errors() {
+        let input = "[CircuitInstruction(operation=Instruction(name='custom', num_qubits=1, num_clbits=0, params=[Parameter(theta)]), qubits=(Qubit(QuantumRegister(1, 'q'), 0),), clbits=())]";
+
+        let qc = QuantumCircuit::new(input.to_string(), None);
+
+        assert_eq!(
+            qc.assign_parameters_by_position(&[1.0, 2.0]),
+            Err(ParameterError::LengthMismatch { expected: 1, found: 2 })
+        );
+        let phi = Parameter::new("phi".to_string());
+        assert_eq!(
+            qc.assign_parameters(&HashMap::from([(phi.clone(), 1.0)])),
+            Err(ParameterError::UnknownParameter(phi))
+        );
+    }
 }
@@ -1,67 +1,128 @@
-use std::collections::HashMap;
-
-use nalgebra::base::DMatrix;
-use crate::c64;
-
 /// TODO: Migrate to a standard parser library instead of a custom one (didn't realized these existed before lol)
 use crate::{
     bit::{AncillaQubit, Bit, BitOps, Clbit, Qubit},
     circuit_instruction::CircuitInstruction,
-    operations::{Delay, Gate, Operation, TimeUnit},
+    error::{ParseError, ParseErrorKind},
+    gates::GateRegistry,
+    operations::{Barrier, Delay, Measurement, Opaque, Operation, Reset, TimeUnit},
+    parameter::{Function, Param, Parameter, ParameterExpression},
 };
 
-use crate::gates::singleton;
+use super::tokenizer::{Span, SpannedToken, Token, Tokenizer};
+use super::UnknownOperationPolicy;
 
-use super::tokenizer::{Token, Tokenizer};
+/// Position of `bit` in `bits`, appending it first if it is not present.
+/// Like Qiskit, the first bit seen of a register appends the whole register
+/// in index order, so bits no instruction touches still have a position.
+fn position_or_insert<T: BitOps + PartialEq>(bits: &mut Vec<T>, bit: T) -> usize {
+    if let Some(position) = bits.iter().position(|existing| *existing == bit) {
+        return position;
+    }
+    let register_seen = bits.iter().any(|existing| {
+        existing.name() == bit.name() && existing.register_size() == bit.register_size()
+    });
+    if !register_seen {
+        bits.extend(
+            (0..bit.register_size()).map(|index| T::new(bit.name(), bit.register_size(), index)),
+        );
+    }
+    match bits.iter().position(|existing| *existing == bit) {
+        Some(position) => position,
+        // an index past the declared register size
+        None => {
+            bits.push(bit);
+            bits.len() - 1
+        }
+    }
+}
 
-macro_rules! insert_gates {
-    ($map:expr, $($gate:ident),*) => {
-        $(
-            $map.insert(
-                singleton::$gate().name().to_string(),
-                singleton::$gate().to_matrix(),
-            );
-        )*
-    };
+/// Attach the instruction index and a source snippet to errors collected
+/// while parsing that instruction.
+fn locate(errors: &mut [ParseError], instruction: usize, source: &[char]) {
+    for error in errors.iter_mut() {
+        *error = error
+            .clone()
+            .with_instruction(instruction)
+            .with_source(source);
+    }
+}
+
+/// An operation as written in the repr, before it is resolved against the
+/// operands of its instruction.
+enum RawOperation {
+    /// `Instruction(name=..., num_qubits=..., num_clbits=..., params=[...])`
+    Instruction {
+        name: String,
+        name_span: Span,
+        num_qubits: usize,
+        num_clbits: usize,
+        params: Vec<Param>,
+        /// The `[unit=...]` following a parameter, as in `Delay`'s repr
+        unit: Option<TimeUnit>,
+    },
+    /// `Delay(duration=...[unit=...])`
+    Delay { duration: f64, unit: TimeUnit },
 }
 
 /// Reads in the tokenized Qiskit circuit data and parses it into a QuantumCircuit object.
 /// This should not be instantiated by itself, but rather through the QuantumCircuit::new() method
 pub struct Parser {
-    tokens: Vec<Token>,
+    source: Vec<char>,
+    tokens: Vec<SpannedToken>,
     pos: usize,
-    mtx_map: HashMap<String, DMatrix<c64>>,
+    registry: GateRegistry,
+    policy: UnknownOperationPolicy,
+    warnings: Vec<ParseError>,
+    mismatches: Vec<ParseError>,
 }
 
 impl Parser {
-    pub fn new(input: String) -> Self {
+    pub fn new(
+        input: String,
+        registry: GateRegistry,
+        policy: UnknownOperationPolicy,
+    ) -> Result<Self, ParseError> {
+        let source: Vec<char> = input.chars().collect();
         let mut tokenizer = Tokenizer::new(input);
-        let tokens = tokenizer.tokenize();
-
-        let mut mtx_map: HashMap<String, DMatrix<c64>> = HashMap::new();
+        let tokens = tokenizer
+            .tokenize()
+            .map_err(|err| err.with_source(&source))?;
 
-        insert_gates!(mtx_map, hadamard, x, y, z, cx);
-
-        Self {
+        Ok(Self {
+            source,
             tokens,
             pos: 0,
-            mtx_map,
-        }
+            registry,
+            policy,
+            warnings: Vec::new(),
+            mismatches: Vec::new(),
+        })
     }
 
     pub fn parse(
         &mut self,
-        gates: &mut Vec<Operation>,
         qubits: &mut Vec<Qubit>,
         clbits: &mut Vec<Clbit>,
-    ) -> Vec<CircuitInstruction> {
+        ancillas: &mut Vec<AncillaQubit>,
+    ) -> Result<Vec<CircuitInstruction>, ParseError> {
         let mut instructions = Vec::new();
-        self.expect_token(Token::OpenBracket);
+        self.expect_token(Token::OpenBracket)
+            .map_err(|err| err.with_source(&self.source))?;
         while self.pos < self.tokens.len() {
             if let Some(token) = self.next_token() {
                 match token {
                     Token::Identifier(id) if id == "CircuitInstruction" => {
-                        let instruction = self.parse_circuit_instruction(gates, qubits, clbits);
+                        let warnings_before = self.warnings.len();
+                        let mismatches_before = self.mismatches.len();
+                        let instruction = self
+                            .parse_circuit_instruction(qubits, clbits, ancillas)
+                            .map_err(|err| {
+                                err.with_instruction(instructions.len())
+                                    .with_source(&self.source)
+                            })?;
+                        let index = instructions.len();
+                        locate(&mut self.warnings[warnings_before..], index, &self.source);
+                        locate(&mut self.mismatches[mismatches_before..], index, &self.source);
                         instructions.push(instruction);
                     }
                     Token::CloseBracket => break,
@@ -69,203 +130,671 @@ impl Parser {
                 }
             }
         }
-        instructions
+
+        // report every inconsistent instruction at once rather than just the
+        // first
+        if let Some(first) = self.mismatches.first() {
+            let span = first.span();
+            return Err(ParseError::new(
+                ParseErrorKind::Validation {
+                    errors: std::mem::take(&mut self.mismatches),
+                },
+                span,
+            ));
+        }
+        Ok(instructions)
+    }
+
+    /// Unknown operations reported under [`UnknownOperationPolicy::Warn`].
+    pub fn warnings(&self) -> &Vec<ParseError> {
+        &self.warnings
     }
 
     fn parse_circuit_instruction(
         &mut self,
-        operations: &mut Vec<Operation>,
         qubits: &mut Vec<Qubit>,
         clbits: &mut Vec<Clbit>,
-    ) -> CircuitInstruction {
-        self.expect_token(Token::OpenParen);
-        let operation = self.parse_operation(operations);
-
-        // Maybe no clone in the future? Overhead should be minimal tho
-        let parsed_qubits: Vec<Qubit> = self
-            .parse_bits("qubits")
-            .iter()
-            .map(|bit| Qubit::from(bit.clone()))
-            .collect();
+        ancillas: &mut Vec<AncillaQubit>,
+    ) -> Result<CircuitInstruction, ParseError> {
+        self.expect_token(Token::OpenParen)?;
+        let raw_operation = self.parse_operation()?;
+
+        let mut parsed_qubits: Vec<Qubit> = Vec::new();
+        for bit in self.parse_bits("qubits")? {
+            match bit {
+                Bit::AncillaQubit(ancilla) => {
+                    position_or_insert(ancillas, ancilla.clone());
+                    parsed_qubits.push(Qubit::from(ancilla));
+                }
+                bit => parsed_qubits.push(Qubit::from(bit)),
+            }
+        }
         let parsed_clbits: Vec<Clbit> = self
-            .parse_bits("clbits")
-            .iter()
-            .map(|bit| Clbit::from(bit.clone()))
+            .parse_bits("clbits")?
+            .into_iter()
+            .map(Clbit::from)
             .collect();
-        self.expect_token(Token::CloseParen);
+        self.expect_token(Token::CloseParen)?;
 
-        for qubit in parsed_qubits.iter() {
-            if !qubits.contains(&qubit) {
-                qubits.push(qubit.clone());
-            }
+        // Instructions refer to bits by their position in the circuit, with
+        // registers appended as their first bit is seen
+        let qubit_indices: Vec<usize> = parsed_qubits
+            .into_iter()
+            .map(|qubit| position_or_insert(qubits, qubit))
+            .collect();
+        let clbit_indices: Vec<usize> = parsed_clbits
+            .into_iter()
+            .map(|clbit| position_or_insert(clbits, clbit))
+            .collect();
+
+        // the repr declares the operand counts; check them against the
+        // operands and, for gates, against the matrix
+        let mut declared = None;
+        if let RawOperation::Instruction {
+            name,
+            name_span,
+            num_qubits,
+            num_clbits,
+            ..
+        } = &raw_operation
+        {
+            self.check_operand_count(name, *name_span, "qubits", *num_qubits, qubit_indices.len());
+            self.check_operand_count(name, *name_span, "clbits", *num_clbits, clbit_indices.len());
+            declared = Some((*name_span, *num_qubits));
         }
-        for clbit in parsed_clbits.iter() {
-            if !clbits.contains(&clbit) {
-                clbits.push(clbit.clone());
+
+        let operation =
+            self.resolve_operation(raw_operation, &qubit_indices, &clbit_indices)?;
+
+        if let (Some((name_span, num_qubits)), Operation::Gate(gate)) = (declared, &operation) {
+            let (rows, cols) = gate.matrix().shape();
+            let expected = 1usize.checked_shl(num_qubits as u32);
+            if rows != cols || Some(rows) != expected {
+                self.mismatches.push(ParseError::new(
+                    ParseErrorKind::MatrixDimensionMismatch {
+                        name: gate.name().clone(),
+                        num_qubits,
+                        dimension: (rows, cols),
+                    },
+                    name_span,
+                ));
             }
         }
 
-        let qubit_indices = qubits.iter().map(|qubit| qubit.index()).collect();
-        let clbit_indices = clbits.iter().map(|clbit| clbit.index()).collect();
+        Ok(CircuitInstruction::new(operation, qubit_indices, clbit_indices))
+    }
 
-        CircuitInstruction::new(operation, qubit_indices, clbit_indices)
+    fn check_operand_count(
+        &mut self,
+        name: &str,
+        span: Span,
+        group: &str,
+        declared: usize,
+        found: usize,
+    ) {
+        if declared != found {
+            self.mismatches.push(ParseError::new(
+                ParseErrorKind::OperandCountMismatch {
+                    name: name.to_string(),
+                    group: group.to_string(),
+                    declared,
+                    found,
+                },
+                span,
+            ));
+        }
     }
 
-    fn parse_operation(&mut self, operations: &mut Vec<Operation>) -> Operation {
-        self.expect_token(Token::Identifier("operation".to_string()));
-        self.expect_token(Token::Equals);
-        self.expect_token(Token::Identifier("Instruction".to_string()));
-        self.expect_token(Token::OpenParen);
+    fn parse_operation(&mut self) -> Result<RawOperation, ParseError> {
+        self.expect_token(Token::Identifier("operation".to_string()))?;
+        self.expect_token(Token::Equals)?;
+        if self.peek_token() == Some(&Token::Identifier("Delay".to_string())) {
+            return self.parse_delay();
+        }
+        self.expect_token(Token::Identifier("Instruction".to_string()))?;
+        self.expect_token(Token::OpenParen)?;
+
+        let name = self.parse_key_value("name", true)?;
+        let name_span = self.tokens[self.pos - 1].span;
+
+        let num_qubits = self.parse_count("num_qubits")?;
+        let num_clbits = self.parse_count("num_clbits")?;
+
+        let (params, unit) = self.parse_params()?;
 
-        let name = self.parse_key_value("name", true).unwrap();
+        self.expect_token(Token::CloseParen)?;
 
-        // unused... will clean up parsing to ignore stuff like this
-        self.parse_key_value("num_qubits", false);
-        self.parse_key_value("num_clbits", false);
+        Ok(RawOperation::Instruction {
+            name,
+            name_span,
+            num_qubits,
+            num_clbits,
+            params,
+            unit,
+        })
+    }
 
-        let params: Vec<f64> = self.parse_params();
+    /// Parse Qiskit's `Delay` repr, e.g. `Delay(duration=100[unit=dt])`.
+    fn parse_delay(&mut self) -> Result<RawOperation, ParseError> {
+        self.expect_token(Token::Identifier("Delay".to_string()))?;
+        self.expect_token(Token::OpenParen)?;
+        self.expect_token(Token::Identifier("duration".to_string()))?;
+        self.expect_token(Token::Equals)?;
+        let duration = self.expect_number()?;
+        let unit = self.parse_unit_suffix()?;
+        self.expect_token(Token::CloseParen)?;
+
+        Ok(RawOperation::Delay { duration, unit })
+    }
 
-        self.expect_token(Token::CloseParen);
+    /// Parse the `[unit=...]` that follows a delay's duration.
+    fn parse_unit_suffix(&mut self) -> Result<TimeUnit, ParseError> {
+        self.expect_token(Token::OpenBracket)?;
+        let unit = self.parse_key_value("unit", false)?;
+        let unit = self.parse_time_unit(&unit)?;
+        self.expect_token(Token::CloseBracket)?;
+        Ok(unit)
+    }
+
+    fn parse_time_unit(&self, unit: &str) -> Result<TimeUnit, ParseError> {
+        unit.parse().map_err(|_| {
+            self.error(ParseErrorKind::UnexpectedToken {
+                expected: "a time unit".to_string(),
+                found: Token::Identifier(unit.to_string()),
+            })
+        })
+    }
+
+    /// Turn a parsed operation into its `Operation` variant, using the
+    /// circuit positions of the instruction's operands where the variant
+    /// needs them.
+    fn resolve_operation(
+        &mut self,
+        raw: RawOperation,
+        qubits: &[usize],
+        clbits: &[usize],
+    ) -> Result<Operation, ParseError> {
+        let (name, name_span, num_qubits, num_clbits, params, unit) = match raw {
+            RawOperation::Delay { duration, unit } => {
+                return Ok(Operation::Delay(Delay::new(duration, unit)))
+            }
+            RawOperation::Instruction {
+                name,
+                name_span,
+                num_qubits,
+                num_clbits,
+                params,
+                unit,
+            } => (name, name_span, num_qubits, num_clbits, params, unit),
+        };
 
         let operation = match name.as_str() {
-            // gates have custom names
-            _ => {
-                let mtx = match self.mtx_map.get(&name) {
-                    Some(_) => self.mtx_map.get(&name).unwrap().clone(),
-                    None => {
-                        let mtx = DMatrix::zeros(2, 2);
-                        self.mtx_map.insert(name.clone(), mtx.clone());
-                        mtx
-                    }
-                };
-                let operation = Operation::Gate(Gate::new(
-                    name.clone(),
-                    params,
-                    None,
-                    TimeUnit::DT,
-                    mtx,
-                    None,
-                ));
-                operations.push(operation);
-                operations.last().unwrap().clone()
+            "measure" => {
+                if qubits.len() != clbits.len() {
+                    return Err(ParseError::new(
+                        ParseErrorKind::MeasureOperandMismatch {
+                            qubits: qubits.len(),
+                            clbits: clbits.len(),
+                        },
+                        name_span,
+                    ));
+                }
+                Operation::Measurement(Measurement::new(
+                    qubits.iter().copied().zip(clbits.iter().copied()).collect(),
+                ))
             }
+            "barrier" => Operation::Barrier(Barrier::new(qubits.to_vec())),
+            "reset" => Operation::Reset(Reset::new(qubits.to_vec())),
+            "delay" => match params.as_slice() {
+                [Param::Value(duration)] => {
+                    Operation::Delay(Delay::new(*duration, unit.unwrap_or(TimeUnit::DT)))
+                }
+                [duration] => {
+                    return Err(ParseError::new(
+                        ParseErrorKind::NonNumericDuration {
+                            found: duration.to_string(),
+                        },
+                        name_span,
+                    ))
+                }
+                _ => {
+                    return Err(self.error(ParseErrorKind::InvalidParams {
+                        name,
+                        expected: 1,
+                        found: params.len(),
+                    }))
+                }
+            },
+            // everything else is a gate, resolved through the registry
+            _ => match self.registry.get(&name, num_qubits, params.len()) {
+                Some(definition) => {
+                    if !definition.accepts(params.len()) {
+                        return Err(self.error(ParseErrorKind::InvalidParams {
+                            name,
+                            expected: definition.num_params().unwrap_or_default(),
+                            found: params.len(),
+                        }));
+                    }
+                    // report a definition for other qubits without building
+                    // it: its matrix may be large
+                    if definition.num_qubits() != num_qubits {
+                        let dimension = 1usize.checked_shl(definition.num_qubits() as u32).unwrap_or(0);
+                        self.mismatches.push(ParseError::new(
+                            ParseErrorKind::MatrixDimensionMismatch {
+                                name: name.clone(),
+                                num_qubits,
+                                dimension: (dimension, dimension),
+                            },
+                            name_span,
+                        ));
+                        return Ok(Operation::Opaque(Opaque::new(name, num_qubits, num_clbits, params)));
+                    }
+                    Operation::Gate(definition.instantiate(params))
+                }
+                None => {
+                    let unknown = ParseError::new(
+                        ParseErrorKind::UnknownOperation { name: name.clone() },
+                        name_span,
+                    );
+                    match self.policy {
+                        UnknownOperationPolicy::Error => return Err(unknown),
+                        UnknownOperationPolicy::Warn => self.warnings.push(unknown),
+                        UnknownOperationPolicy::Opaque => {}
+                    }
+                    Operation::Opaque(Opaque::new(name, num_qubits, num_clbits, params))
+                }
+            },
         };
-        operation
+        Ok(operation)
     }
 
-    fn parse_bits(&mut self, group_name: &str) -> Vec<Bit> {
-        self.expect_token(Token::Comma);
-        self.expect_token(Token::Identifier(group_name.to_string()));
-        self.expect_token(Token::Equals);
-        self.expect_token(Token::OpenParen);
+    fn parse_bits(&mut self, group_name: &str) -> Result<Vec<Bit>, ParseError> {
+        self.expect_token(Token::Comma)?;
+        self.expect_token(Token::Identifier(group_name.to_string()))?;
+        self.expect_token(Token::Equals)?;
+        self.expect_token(Token::OpenParen)?;
 
         let mut group = Vec::new();
 
-        while let Some(Token::Identifier(bit_type)) = self.next_token() {
-            if bit_type == "Qubit" || bit_type == "Clbit" || bit_type == "AncillaQubit" {
-                self.expect_token(Token::OpenParen);
-                self.expect_token(Token::Identifier("QuantumRegister".to_string()));
-                self.expect_token(Token::OpenParen);
-                self.expect_number();
-                self.expect_token(Token::Comma);
-
-                let name = self.expect_string();
-                self.expect_token(Token::CloseParen);
-                self.expect_token(Token::Comma);
-
-                let index = self.expect_number() as usize;
-                self.expect_token(Token::CloseParen);
-                self.expect_token(Token::Comma);
-                match bit_type.as_str() {
-                    "Qubit" => {
-                        group.push(Bit::Qubit(Qubit::new(name, index)));
-                        self.expect_token(Token::CloseParen);
-                        break;
-                    }
-                    "Clbit" => {
-                        group.push(Bit::Clbit(Clbit::new(name, index)));
-                        self.expect_token(Token::CloseParen);
-                        break;
-                    }
-                    "AncillaQubit" => {
-                        group.push(Bit::AncillaQubit(AncillaQubit::new(name, index)));
-                        break;
-                    }
-                    _ => panic!("Unexpected bit type: {:?}", bit_type),
+        // Python tuples: `()`, `(bit,)` or `(bit, bit, ...)` with an optional
+        // trailing comma
+        loop {
+            if self.peek_token() == Some(&Token::CloseParen) {
+                self.pos += 1;
+                break;
+            }
+
+            group.push(self.parse_bit(group_name)?);
+
+            match self.next_token() {
+                Some(Token::Comma) => continue,
+                Some(Token::CloseParen) => break,
+                Some(token) => {
+                    return Err(self.error(ParseErrorKind::UnexpectedToken {
+                        expected: "',' or ')'".to_string(),
+                        found: token,
+                    }))
                 }
-            } else {
-                panic!("Unexpected bit type format: {:?}", bit_type);
+                None => return Err(self.eof("',' or ')'".to_string())),
+            }
+        }
+        Ok(group)
+    }
+
+    /// Parse a single bit, e.g. `Qubit(QuantumRegister(2, 'q'), 0)`. Only
+    /// quantum bits are accepted in the `qubits` group and classical bits in
+    /// the `clbits` group.
+    fn parse_bit(&mut self, group_name: &str) -> Result<Bit, ParseError> {
+        let bit_type = match self.next_token() {
+            Some(Token::Identifier(bit_type)) => bit_type,
+            Some(token) => {
+                return Err(self.error(ParseErrorKind::UnexpectedToken {
+                    expected: "a bit".to_string(),
+                    found: token,
+                }))
             }
+            None => return Err(self.eof("a bit".to_string())),
+        };
+        let register = match bit_type.as_str() {
+            "Qubit" => "QuantumRegister",
+            "Clbit" => "ClassicalRegister",
+            "AncillaQubit" => "AncillaRegister",
+            _ => return Err(self.error(ParseErrorKind::UnknownBitType { found: bit_type })),
+        };
+        let is_quantum = bit_type != "Clbit";
+        if is_quantum != (group_name == "qubits") {
+            return Err(self.error(ParseErrorKind::UnexpectedBitType {
+                found: bit_type,
+                group: group_name.to_string(),
+            }));
         }
-        group
+
+        self.expect_token(Token::OpenParen)?;
+        self.expect_token(Token::Identifier(register.to_string()))?;
+        self.expect_token(Token::OpenParen)?;
+        let register_size = self.expect_count()?;
+        self.expect_token(Token::Comma)?;
+
+        let name = self.expect_string()?;
+        self.expect_token(Token::CloseParen)?;
+        self.expect_token(Token::Comma)?;
+
+        let index = self.expect_count()?;
+        self.expect_token(Token::CloseParen)?;
+
+        Ok(match bit_type.as_str() {
+            "Qubit" => Bit::Qubit(Qubit::new(name, register_size, index)),
+            "Clbit" => Bit::Clbit(Clbit::new(name, register_size, index)),
+            _ => Bit::AncillaQubit(AncillaQubit::new(name, register_size, index)),
+        })
     }
 
-    fn parse_params(&mut self) -> Vec<f64> {
-        self.expect_token(Token::Comma);
-        self.expect_token(Token::Identifier("params".to_string()));
-        self.expect_token(Token::Equals);
-        self.expect_token(Token::OpenBracket);
+    /// Parse `, params=[...]`, along with the `[unit=...]` a delay's duration
+    /// may carry.
+    fn parse_params(&mut self) -> Result<(Vec<Param>, Option<TimeUnit>), ParseError> {
+        self.expect_token(Token::Comma)?;
+        self.expect_token(Token::Identifier("params".to_string()))?;
+        self.expect_token(Token::Equals)?;
+        self.expect_token(Token::OpenBracket)?;
 
         let mut params = Vec::new();
+        let mut unit = None;
 
         // Catches no parameters
-        if self.tokens[self.pos] == Token::CloseBracket {
+        if self.peek_token() == Some(&Token::CloseBracket) {
+            self.pos += 1;
+            return Ok((params, unit));
+        }
+
+        loop {
+            params.push(self.parse_param()?);
+            if self.peek_token() == Some(&Token::OpenBracket) {
+                unit = Some(self.parse_unit_suffix()?);
+            }
+            match self.next_token() {
+                Some(Token::Comma) => continue,
+                Some(Token::CloseBracket) => break,
+                Some(token) => {
+                    return Err(self.error(ParseErrorKind::UnexpectedToken {
+                        expected: "',' or ']'".to_string(),
+                        found: token,
+                    }))
+                }
+                None => return Err(self.eof("',' or ']'".to_string())),
+            }
+        }
+        Ok((params, unit))
+    }
+
+    /// Parse a single parameter: a number, `Parameter(theta)`,
+    /// `ParameterVectorElement(theta[0])` or `ParameterExpression(...)`.
+    fn parse_param(&mut self) -> Result<Param, ParseError> {
+        match self.next_token() {
+            Some(Token::Number(n)) => Ok(Param::Value(n)),
+            Some(Token::Identifier(id))
+                if id == "Parameter" || id == "ParameterVectorElement" =>
+            {
+                self.expect_token(Token::OpenParen)?;
+                let parameter = self.parse_symbol()?;
+                self.expect_token(Token::CloseParen)?;
+                Ok(Param::Expression(ParameterExpression::Symbol(parameter)))
+            }
+            Some(Token::Identifier(id)) if id == "ParameterExpression" => {
+                self.expect_token(Token::OpenParen)?;
+                let expr = self.parse_expression()?;
+                self.expect_token(Token::CloseParen)?;
+                Ok(Param::Expression(expr))
+            }
+            Some(token) => Err(self.error(ParseErrorKind::UnexpectedToken {
+                expected: "a parameter".to_string(),
+                found: token,
+            })),
+            None => Err(self.eof("a parameter".to_string())),
+        }
+    }
+
+    /// Parse a parameter name, including the `[index]` suffix of vector
+    /// elements.
+    fn parse_symbol(&mut self) -> Result<Parameter, ParseError> {
+        let mut name = match self.next_token() {
+            Some(Token::Identifier(name)) => name,
+            // `inf` and `nan` read as floats, but are valid parameter names
+            Some(Token::Number(_)) if matches!(self.previous_text().as_str(), "inf" | "nan") => {
+                self.previous_text()
+            }
+            Some(token) => {
+                return Err(self.error(ParseErrorKind::UnexpectedToken {
+                    expected: "a parameter name".to_string(),
+                    found: token,
+                }))
+            }
+            None => return Err(self.eof("a parameter name".to_string())),
+        };
+        if self.peek_token() == Some(&Token::OpenBracket) {
+            self.pos += 1;
+            let index = self.expect_number()?;
+            self.expect_token(Token::CloseBracket)?;
+            name = format!("{}[{}]", name, index);
+        }
+        Ok(Parameter::new(name))
+    }
+
+    /// Parse a sum or difference of terms. Operator precedence follows
+    /// Python: `**` binds tighter than unary minus, which binds tighter than
+    /// `*` and `/`.
+    fn parse_expression(&mut self) -> Result<ParameterExpression, ParseError> {
+        let mut expr = self.parse_term()?;
+        loop {
+            match self.peek_token() {
+                Some(Token::Plus) => {
+                    self.pos += 1;
+                    let rhs = self.parse_term()?;
+                    expr = ParameterExpression::Add(Box::new(expr), Box::new(rhs));
+                }
+                Some(Token::Minus) => {
+                    self.pos += 1;
+                    let rhs = self.parse_term()?;
+                    expr = ParameterExpression::Sub(Box::new(expr), Box::new(rhs));
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    fn parse_term(&mut self) -> Result<ParameterExpression, ParseError> {
+        let mut expr = self.parse_unary()?;
+        loop {
+            match self.peek_token() {
+                Some(Token::Star) => {
+                    self.pos += 1;
+                    let rhs = self.parse_unary()?;
+                    expr = ParameterExpression::Mul(Box::new(expr), Box::new(rhs));
+                }
+                Some(Token::Slash) => {
+                    self.pos += 1;
+                    let rhs = self.parse_unary()?;
+                    expr = ParameterExpression::Div(Box::new(expr), Box::new(rhs));
+                }
+                _ => return Ok(expr),
+            }
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<ParameterExpression, ParseError> {
+        match self.peek_token() {
+            Some(Token::Minus) => {
+                self.pos += 1;
+                Ok(ParameterExpression::Neg(Box::new(self.parse_unary()?)))
+            }
+            Some(Token::Plus) => {
+                self.pos += 1;
+                self.parse_unary()
+            }
+            _ => self.parse_power(),
+        }
+    }
+
+    fn parse_power(&mut self) -> Result<ParameterExpression, ParseError> {
+        let base = self.parse_atom()?;
+        if self.peek_token() == Some(&Token::DoubleStar) {
             self.pos += 1;
-            return params;
+            // right associative, and the exponent may carry a sign
+            let exponent = self.parse_unary()?;
+            return Ok(ParameterExpression::Pow(Box::new(base), Box::new(exponent)));
         }
+        Ok(base)
+    }
 
-        while let Some(Token::Number(n)) = self.next_token() {
-            params.push(n);
+    fn parse_atom(&mut self) -> Result<ParameterExpression, ParseError> {
+        match self.peek_token().cloned() {
+            Some(Token::Number(n)) => {
+                self.pos += 1;
+                // a signed literal binds looser than `**`, e.g. `-2**2 == -4`
+                if n.is_sign_negative() && self.peek_token() == Some(&Token::DoubleStar) {
+                    self.pos += 1;
+                    let exponent = self.parse_unary()?;
+                    return Ok(ParameterExpression::Neg(Box::new(ParameterExpression::Pow(
+                        Box::new(ParameterExpression::Constant(-n)),
+                        Box::new(exponent),
+                    ))));
+                }
+                Ok(ParameterExpression::Constant(n))
+            }
+            Some(Token::OpenParen) => {
+                self.pos += 1;
+                let expr = self.parse_expression()?;
+                self.expect_token(Token::CloseParen)?;
+                Ok(expr)
+            }
+            Some(Token::Identifier(id)) if id == "pi" => {
+                self.pos += 1;
+                Ok(ParameterExpression::Constant(std::f64::consts::PI))
+            }
+            Some(Token::Identifier(id)) => match Function::from_name(&id) {
+                Some(function) if self.tokens.get(self.pos + 1).map(|t| &t.token)
+                    == Some(&Token::OpenParen) =>
+                {
+                    self.pos += 2;
+                    let arg = self.parse_expression()?;
+                    self.expect_token(Token::CloseParen)?;
+                    Ok(ParameterExpression::Call(function, Box::new(arg)))
+                }
+                _ => Ok(ParameterExpression::Symbol(self.parse_symbol()?)),
+            },
+            Some(token) => {
+                self.pos += 1;
+                Err(self.error(ParseErrorKind::UnexpectedToken {
+                    expected: "an expression".to_string(),
+                    found: token,
+                }))
+            }
+            None => Err(self.eof("an expression".to_string())),
         }
+    }
 
-        self.expect_token(Token::CloseBracket);
-        params
+    /// Parse a `key=<integer>` pair such as `num_qubits=2`.
+    fn parse_count(&mut self, key: &str) -> Result<usize, ParseError> {
+        if self.peek_token() == Some(&Token::Comma) {
+            self.pos += 1;
+        }
+        self.expect_token(Token::Identifier(key.to_string()))?;
+        self.expect_token(Token::Equals)?;
+        self.expect_count()
     }
 
-    fn parse_key_value(&mut self, key: &str, is_string: bool) -> Option<String> {
-        if self.tokens[self.pos] == Token::Comma {
+    fn parse_key_value(&mut self, key: &str, is_string: bool) -> Result<String, ParseError> {
+        if self.peek_token() == Some(&Token::Comma) {
             self.pos += 1;
         }
-        self.expect_token(Token::Identifier(key.to_string()));
-        self.expect_token(Token::Equals);
+        self.expect_token(Token::Identifier(key.to_string()))?;
+        self.expect_token(Token::Equals)?;
         if is_string {
-            Some(self.expect_string())
-        } else {
-            self.next_token().map(|token| match token {
-                Token::Number(n) => n.to_string(),
-                Token::StringLiteral(s) => s,
-                Token::Identifier(id) => id,
-                _ => panic!("Unexpected token in key-value parsing: {:?}", token),
-            })
+            return self.expect_string();
+        }
+        match self.next_token() {
+            Some(Token::Number(n)) => Ok(n.to_string()),
+            Some(Token::StringLiteral(s)) => Ok(s),
+            Some(Token::Identifier(id)) => Ok(id),
+            Some(token) => Err(self.error(ParseErrorKind::UnexpectedToken {
+                expected: format!("a value for '{}'", key),
+                found: token,
+            })),
+            None => Err(self.eof(format!("a value for '{}'", key))),
         }
     }
 
-    fn expect_token(&mut self, expected: Token) {
-        let token = self.next_token().expect("Expected token but got None");
-        if token != expected {
-            panic!("Expected token {:?} but got {:?}", expected, token);
+    fn expect_token(&mut self, expected: Token) -> Result<(), ParseError> {
+        match self.next_token() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(self.error(ParseErrorKind::UnexpectedToken {
+                expected: format!("{:?}", expected),
+                found: token,
+            })),
+            None => Err(self.eof(format!("{:?}", expected))),
         }
     }
 
-    fn expect_string(&mut self) -> String {
-        if let Some(Token::StringLiteral(s)) = self.next_token() {
-            s
-        } else {
-            panic!("Expected string but got None")
+    fn expect_string(&mut self) -> Result<String, ParseError> {
+        match self.next_token() {
+            Some(Token::StringLiteral(s)) => Ok(s),
+            Some(token) => Err(self.error(ParseErrorKind::UnexpectedToken {
+                expected: "a string".to_string(),
+                found: token,
+            })),
+            None => Err(self.eof("a string".to_string())),
         }
     }
 
-    fn expect_number(&mut self) -> f64 {
-        if let Some(Token::Number(n)) = self.next_token() {
-            n
-        } else {
-            panic!("Expected number but got None")
+    fn expect_number(&mut self) -> Result<f64, ParseError> {
+        match self.next_token() {
+            Some(Token::Number(n)) => Ok(n),
+            Some(token) => Err(self.error(ParseErrorKind::UnexpectedToken {
+                expected: "a number".to_string(),
+                found: token,
+            })),
+            None => Err(self.eof("a number".to_string())),
         }
     }
 
+    /// Read a non-negative integer, e.g. a register size or a bit index.
+    fn expect_count(&mut self) -> Result<usize, ParseError> {
+        let count = self.expect_number()?;
+        // NaN and infinities have a NaN fractional part
+        if count < 0.0 || count.fract() != 0.0 || count > usize::MAX as f64 {
+            return Err(self.error(ParseErrorKind::InvalidNumber {
+                literal: self.previous_text(),
+            }));
+        }
+        Ok(count as usize)
+    }
+
+    /// Build an error located at the most recently consumed token.
+    fn error(&self, kind: ParseErrorKind) -> ParseError {
+        let span = match self.pos.checked_sub(1).and_then(|pos| self.tokens.get(pos)) {
+            Some(token) => token.span,
+            None => Span::new(0, 0),
+        };
+        ParseError::new(kind, span)
+    }
+
+    fn eof(&self, expected: String) -> ParseError {
+        let end = self.source.len();
+        ParseError::new(ParseErrorKind::UnexpectedEof { expected }, Span::new(end, end))
+    }
+
+    fn peek_token(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|spanned| &spanned.token)
+    }
+
+    /// The source text of the token just consumed.
+    fn previous_text(&self) -> String {
+        let span = &self.tokens[self.pos - 1].span;
+        self.source[span.start..span.end].iter().collect()
+    }
+
     fn next_token(&mut self) -> Option<Token> {
         if self.pos < self.tokens.len() {
-            let token = self.tokens[self.pos].clone();
+            let token = self.tokens[self.pos].token.clone();
             self.pos += 1;
             Some(token)
         } else {
@@ -1,3 +1,5 @@
+use crate::error::{ParseError, ParseErrorKind};
+
 /// Generic Tokens for parsing a Qiskit circuit.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token {
@@ -7,15 +9,43 @@ pub enum Token {
     CloseParen,
     Comma,
     Equals,
+    Plus,
+    Minus,
+    Star,
+    DoubleStar,
+    Slash,
     Identifier(String),
     StringLiteral(String),
     Number(f64),
 }
 
+/// Half-open range of character offsets into the tokenized input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A token together with the location it was read from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpannedToken {
+    pub token: Token,
+    pub span: Span,
+}
+
 /// Tokenizer for parsing a Qiskit circuit.
 pub struct Tokenizer {
     input: Vec<char>,
     pos: usize,
+    /// Whether the previous token can end an operand, in which case a
+    /// following `-` or `+` is a binary operator rather than a sign
+    after_operand: bool,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
 }
 
 impl Tokenizer {
@@ -23,87 +53,333 @@ impl Tokenizer {
         Self {
             input: input.chars().collect(),
             pos: 0,
+            after_operand: false,
         }
     }
 
-    pub fn next_token(&mut self) -> Option<Token> {
+    pub fn next_token(&mut self) -> Result<Option<SpannedToken>, ParseError> {
         while self.pos < self.input.len() {
-            match self.input[self.pos] {
+            let start = self.pos;
+            let token = match self.input[self.pos] {
                 '[' => {
                     self.pos += 1;
-                    return Some(Token::OpenBracket);
+                    Token::OpenBracket
                 }
                 ']' => {
                     self.pos += 1;
-                    return Some(Token::CloseBracket);
+                    Token::CloseBracket
                 }
                 '(' => {
                     self.pos += 1;
-                    return Some(Token::OpenParen);
+                    Token::OpenParen
                 }
                 ')' => {
                     self.pos += 1;
-                    return Some(Token::CloseParen);
+                    Token::CloseParen
                 }
                 ',' => {
                     self.pos += 1;
-                    return Some(Token::Comma);
+                    Token::Comma
                 }
                 '=' => {
                     self.pos += 1;
-                    return Some(Token::Equals);
+                    Token::Equals
                 }
                 '\'' => {
                     self.pos += 1;
-                    let start = self.pos;
                     while self.pos < self.input.len() && self.input[self.pos] != '\'' {
                         self.pos += 1;
                     }
-                    let end = self.pos;
+                    if self.pos >= self.input.len() {
+                        return Err(ParseError::new(
+                            ParseErrorKind::UnterminatedString,
+                            Span::new(start, self.pos),
+                        ));
+                    }
                     self.pos += 1;
-                    return Some(Token::StringLiteral(
-                        self.input[start..end].iter().collect(),
-                    ));
+                    Token::StringLiteral(self.input[start + 1..self.pos - 1].iter().collect())
                 }
-                c if c.is_digit(10) || c == '.' => {
-                    let start = self.pos;
-                    while self.pos < self.input.len()
-                        && (self.input[self.pos].is_digit(10) || self.input[self.pos] == '.')
-                    {
-                        self.pos += 1;
-                    }
-                    let end = self.pos;
-                    return Some(Token::Number(
-                        self.input[start..end]
-                            .iter()
-                            .collect::<String>()
-                            .parse()
-                            .unwrap(),
-                    ));
+                c if c.is_ascii_digit() || c == '.' || self.is_signed_number() => {
+                    self.read_number()?
+                }
+                '+' => {
+                    self.pos += 1;
+                    Token::Plus
+                }
+                '-' => {
+                    self.pos += 1;
+                    Token::Minus
+                }
+                '*' if self.input.get(self.pos + 1) == Some(&'*') => {
+                    self.pos += 2;
+                    Token::DoubleStar
+                }
+                '*' => {
+                    self.pos += 1;
+                    Token::Star
+                }
+                '/' => {
+                    self.pos += 1;
+                    Token::Slash
                 }
                 c if c.is_alphabetic() || c == '_' => {
-                    let start = self.pos;
                     while self.pos < self.input.len()
                         && (self.input[self.pos].is_alphanumeric() || self.input[self.pos] == '_')
                     {
                         self.pos += 1;
                     }
-                    let end = self.pos;
-                    return Some(Token::Identifier(self.input[start..end].iter().collect()));
+                    let ident: String = self.input[start..self.pos].iter().collect();
+                    match ident.as_str() {
+                        "inf" | "nan" => Token::Number(ident.parse().unwrap()),
+                        "np" | "numpy" if self.peek_char() == Some('.') => {
+                            self.read_numpy_scalar(start)?
+                        }
+                        _ => Token::Identifier(ident),
+                    }
                 }
                 _ => {
                     self.pos += 1;
+                    continue;
+                }
+            };
+            self.after_operand = matches!(
+                token,
+                Token::CloseParen
+                    | Token::CloseBracket
+                    | Token::Identifier(_)
+                    | Token::StringLiteral(_)
+                    | Token::Number(_)
+            );
+            return Ok(Some(SpannedToken {
+                token,
+                span: Span::new(start, self.pos),
+            }));
+        }
+        Ok(None)
+    }
+
+    /// Whether the current character is a sign that begins a number, e.g. the
+    /// `-` in `-1.5` or `-inf`, rather than the operator in `theta-1.5`.
+    fn is_signed_number(&self) -> bool {
+        if self.after_operand || !matches!(self.input[self.pos], '-' | '+') {
+            return false;
+        }
+        match self.input.get(self.pos + 1) {
+            Some(c) if c.is_ascii_digit() || *c == '.' => true,
+            _ => self.special_float_at(self.pos + 1),
+        }
+    }
+
+    /// Whether `inf` or `nan` starts at `pos` as a whole word, so that
+    /// `-inf` is a number but `-information` is not.
+    fn special_float_at(&self, pos: usize) -> bool {
+        (self.starts_with_at(pos, "inf") || self.starts_with_at(pos, "nan"))
+            && !self
+                .input
+                .get(pos + 3)
+                .is_some_and(|c| c.is_alphanumeric() || *c == '_')
+    }
+
+    /// Read a Python float repr: an optional sign followed by either `inf`,
+    /// `nan` or a decimal mantissa with an optional exponent.
+    fn read_number(&mut self) -> Result<Token, ParseError> {
+        let start = self.pos;
+        if matches!(self.input[self.pos], '-' | '+') {
+            self.pos += 1;
+        }
+
+        if self.special_float_at(self.pos) {
+            self.pos += 3;
+        } else {
+            while self.pos < self.input.len()
+                && (self.input[self.pos].is_ascii_digit() || self.input[self.pos] == '.')
+            {
+                self.pos += 1;
+            }
+            if matches!(self.peek_char(), Some('e' | 'E')) {
+                let mut exp_end = self.pos + 1;
+                if matches!(self.input.get(exp_end), Some('-' | '+')) {
+                    exp_end += 1;
+                }
+                if self.input.get(exp_end).is_some_and(|c| c.is_ascii_digit()) {
+                    self.pos = exp_end;
+                    while self.pos < self.input.len() && self.input[self.pos].is_ascii_digit() {
+                        self.pos += 1;
+                    }
                 }
             }
         }
-        None
+
+        let literal: String = self.input[start..self.pos].iter().collect();
+        match literal.parse() {
+            Ok(n) => Ok(Token::Number(n)),
+            Err(_) => Err(ParseError::new(
+                ParseErrorKind::InvalidNumber { literal },
+                Span::new(start, self.pos),
+            )),
+        }
     }
 
-    pub fn tokenize(&mut self) -> Vec<Token> {
+    /// Read the NumPy 2 scalar repr, e.g. `np.float64(0.5)`, as a plain
+    /// number. `start` is the offset of the `np` prefix, which has already
+    /// been consumed.
+    fn read_numpy_scalar(&mut self, start: usize) -> Result<Token, ParseError> {
+        // skip the '.' between the module and the dtype
+        self.pos += 1;
+        while self.pos < self.input.len() && self.input[self.pos].is_alphanumeric() {
+            self.pos += 1;
+        }
+        self.skip_whitespace();
+
+        let mut value = None;
+        if self.peek_char() == Some('(') {
+            self.pos += 1;
+            self.skip_whitespace();
+            if self.pos < self.input.len() {
+                value = self.read_number().ok();
+            }
+            self.skip_whitespace();
+        }
+
+        match (value, self.peek_char()) {
+            (Some(number), Some(')')) => {
+                self.pos += 1;
+                Ok(number)
+            }
+            _ => Err(ParseError::new(
+                ParseErrorKind::InvalidNumber {
+                    literal: self.input[start..self.pos].iter().collect(),
+                },
+                Span::new(start, self.pos),
+            )),
+        }
+    }
+
+    fn starts_with_at(&self, pos: usize, pattern: &str) -> bool {
+        let mut chars = self.input.iter().skip(pos);
+        pattern.chars().all(|expected| chars.next() == Some(&expected))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.input.len() && self.input[self.pos].is_whitespace() {
+            self.pos += 1;
+        }
+    }
+
+    fn peek_char(&self) -> Option<char> {
+        self.input.get(self.pos).copied()
+    }
+
+    pub fn tokenize(&mut self) -> Result<Vec<SpannedToken>, ParseError> {
         let mut tokens = Vec::new();
-        while let Some(token) = self.next_token() {
+        while let Some(token) = self.next_token()? {
             tokens.push(token);
         }
-        tokens
+        Ok(tokens)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn numbers(input: &str) -> Vec<f64> {
+        Tokenizer::new(input.to_string())
+            .tokenize()
+            .unwrap()
+            .into_iter()
+            .filter_map(|spanned| match spanned.token {
+                Token::Number(n) => Some(n),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Signs, exponents and the Python special floats
+    #[test]
+    fn test_python_float_reprs() {
+        let parsed = numbers("[-1.5707963267948966, 1e-07, 2.5E+3, +0.5, -0.0, 3]");
+        assert_eq!(parsed, vec![-std::f64::consts::FRAC_PI_2, 1e-07, 2500.0, 0.5, -0.0, 3.0]);
+
+        let special = numbers("[inf, -inf, nan]");
+        assert_eq!(special[0], f64::INFINITY);
+        assert_eq!(special[1], f64::NEG_INFINITY);
+        assert!(special[2].is_nan());
+    }
+
+    /// Identifiers that merely start with `inf` or `nan` are not numbers
+    #[test]
+    fn test_special_float_prefix() {
+        let tokens = Tokenizer::new("[-information, +nan_1, infinity]".to_string())
+            .tokenize()
+            .unwrap();
+        let parsed: Vec<Token> = tokens.into_iter().map(|spanned| spanned.token).collect();
+        assert_eq!(parsed, vec![
+            Token::OpenBracket,
+            Token::Minus,
+            Token::Identifier("information".to_string()),
+            Token::Comma,
+            Token::Plus,
+            Token::Identifier("nan_1".to_string()),
+            Token::Comma,
+            Token::Identifier("infinity".to_string()),
+            Token::CloseBracket,
+        ]);
+    }
+
+    /// NumPy 2 wraps scalars in their dtype, e.g. `np.float64(0.5)`
+    #[test]
+    fn test_numpy_scalar_repr() {
+        let tokens = Tokenizer::new("[np.float64(-0.25), np.float32( 1e-3 )]".to_string())
+            .tokenize()
+            .unwrap();
+        let parsed: Vec<Token> = tokens.into_iter().map(|spanned| spanned.token).collect();
+        assert_eq!(parsed, vec![
+            Token::OpenBracket,
+            Token::Number(-0.25),
+            Token::Comma,
+            Token::Number(1e-3),
+            Token::CloseBracket,
+        ]);
+    }
+
+    /// A NumPy wrapper around something other than a number is rejected
+    #[test]
+    fn test_numpy_scalar_invalid() {
+        let err = Tokenizer::new("[np.float64('a')]".to_string())
+            .tokenize()
+            .unwrap_err();
+        assert!(matches!(err.kind(), ParseErrorKind::InvalidNumber { .. }));
+        assert_eq!(err.span().start, 1);
+    }
+
+    /// An `e` that is not followed by digits is not part of the number
+    #[test]
+    fn test_exponent_requires_digits() {
+        let tokens = Tokenizer::new("1e".to_string()).tokenize().unwrap();
+        let parsed: Vec<Token> = tokens.into_iter().map(|spanned| spanned.token).collect();
+        assert_eq!(parsed, vec![Token::Number(1.0), Token::Identifier("e".to_string())]);
+    }
+
+    /// Signs directly after an operand are operators, not part of a number
+    #[test]
+    fn test_binary_minus() {
+        let tokens = Tokenizer::new("(theta-0.5, -2**x/3)".to_string())
+            .tokenize()
+            .unwrap();
+        let parsed: Vec<Token> = tokens.into_iter().map(|spanned| spanned.token).collect();
+        assert_eq!(parsed, vec![
+            Token::OpenParen,
+            Token::Identifier("theta".to_string()),
+            Token::Minus,
+            Token::Number(0.5),
+            Token::Comma,
+            Token::Number(-2.0),
+            Token::DoubleStar,
+            Token::Identifier("x".to_string()),
+            Token::Slash,
+            Token::Number(3.0),
+            Token::CloseParen,
+        ]);
     }
 }
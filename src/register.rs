@@ -0,0 +1,69 @@
+use crate::bit::{BitOps, Clbit, Qubit};
+
+/// A named register of quantum bits
+#[derive(Debug, PartialEq, Clone)]
+pub struct QuantumRegister {
+    name: String,
+    size: usize,
+    bits: Vec<Qubit>,
+}
+
+/// A named register of classical bits
+#[derive(Debug, PartialEq, Clone)]
+pub struct ClassicalRegister {
+    name: String,
+    size: usize,
+    bits: Vec<Clbit>,
+}
+
+impl QuantumRegister {
+    pub fn new(name: String, size: usize) -> Self {
+        let bits = (0..size)
+            .map(|index| Qubit::new(name.clone(), size, index))
+            .collect();
+        QuantumRegister { name, size, bits }
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn bits(&self) -> &Vec<Qubit> {
+        &self.bits
+    }
+
+    /// Index of `qubit` within this register, if it belongs to it.
+    pub fn index_of(&self, qubit: &Qubit) -> Option<usize> {
+        self.bits.iter().position(|bit| bit == qubit)
+    }
+}
+
+impl ClassicalRegister {
+    pub fn new(name: String, size: usize) -> Self {
+        let bits = (0..size)
+            .map(|index| Clbit::new(name.clone(), size, index))
+            .collect();
+        ClassicalRegister { name, size, bits }
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn bits(&self) -> &Vec<Clbit> {
+        &self.bits
+    }
+
+    /// Index of `clbit` within this register, if it belongs to it.
+    pub fn index_of(&self, clbit: &Clbit) -> Option<usize> {
+        self.bits.iter().position(|bit| bit == clbit)
+    }
+}